@@ -26,10 +26,62 @@
 
 extern crate std;
 
-#[derive(Serialize, Deserialize)]
+use super::condition::Condition;
+use super::filesystem::shell_quote as quote;
+use super::version::{Version, VersionSource};
+
+pub type Followers = std::collections::BTreeMap<std::path::PathBuf,
+                                                 std::path::PathBuf>;
+
+pub static WRAPPER_MARKER: &str =
+    "# generated by update-alternatives, do not edit";
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Default)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum LinkKind {
+    #[default]
+    Symlink,
+    Wrapper {
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        env: std::collections::BTreeMap<String, String>,
+    },
+}
+
+impl LinkKind {
+    pub fn is_symlink(&self) -> bool {
+        *self == LinkKind::Symlink
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone)]
 pub struct Alternative {
     target: std::path::PathBuf,
     priority: i32,
+    #[serde(default, skip_serializing_if = "Followers::is_empty")]
+    followers: Followers,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<std::path::PathBuf>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    owners: Vec<String>,
+    // whether the alternative was also added without an owner, which keeps
+    // it from being removed along with its last owner
+    #[serde(default, skip_serializing_if = "is_false")]
+    added_by_hand: bool,
+    #[serde(default, skip_serializing_if = "LinkKind::is_symlink")]
+    kind: LinkKind,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    conditions: Vec<Condition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<Version>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    priority_from: Option<VersionSource>,
+    // when the alternative was first added, in seconds since the epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    added: Option<u64>,
 }
 
 impl Alternative {
@@ -37,7 +89,107 @@ impl Alternative {
         target: P, priority: i32
     ) -> Alternative {
         Alternative{ target: std::path::PathBuf::from(target.as_ref()),
-                     priority }
+                     priority, followers: Followers::new(), source: None,
+                     owners: Vec::new(), added_by_hand: false,
+                     kind: LinkKind::Symlink,
+                     tags: Vec::new(), conditions: Vec::new(),
+                     version: None, priority_from: None,
+                     added: None }
+    }
+
+    pub fn from_drop_in<P: std::convert::AsRef<std::path::Path>,
+                        Q: std::convert::AsRef<std::path::Path>>(
+        target: P, priority: i32, followers: Followers, source: Q
+    ) -> Alternative {
+        Alternative{ target: std::path::PathBuf::from(target.as_ref()),
+                     priority, followers,
+                     source: Some(std::path::PathBuf::from(source.as_ref())),
+                     owners: Vec::new(), added_by_hand: false,
+                     kind: LinkKind::Symlink,
+                     tags: Vec::new(), conditions: Vec::new(),
+                     version: None, priority_from: None,
+                     added: None }
+    }
+
+    pub fn owned_by(mut self, owner: &str) -> Alternative {
+        self.add_owner(owner);
+
+        self
+    }
+
+    pub fn with_kind(mut self, kind: LinkKind) -> Alternative {
+        self.kind = kind;
+
+        self
+    }
+
+    pub fn with_tags<I: IntoIterator<Item = String>>(mut self, tags: I)
+    -> Alternative {
+        for tag in tags {
+            if !self.tags.contains(&tag) {
+                self.tags.push(tag);
+            }
+        }
+
+        self
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn with_conditions<I: IntoIterator<Item = Condition>>(
+        mut self, conditions: I
+    ) -> Alternative {
+        for condition in conditions {
+            if !self.conditions.contains(&condition) {
+                self.conditions.push(condition);
+            }
+        }
+
+        self
+    }
+
+    // the conditions that must all hold for the alternative to be eligible
+    pub fn conditions(&self) -> &[Condition] {
+        &self.conditions
+    }
+
+    // derives the priority from version, which was found in source, so that
+    // it can be derived again when the target changes
+    pub fn with_version(mut self, source: VersionSource,
+                        version: Version) -> Alternative {
+        self.priority_from = Some(source);
+        self.set_version(version);
+
+        self
+    }
+
+    pub fn version(&self) -> Option<&Version> {
+        self.version.as_ref()
+    }
+
+    pub fn priority_from(&self) -> Option<VersionSource> {
+        self.priority_from
+    }
+
+    pub fn set_version(&mut self, version: Version) -> bool {
+        if self.version.as_ref() == Some(&version) {
+            return false;
+        }
+
+        self.priority = version.priority();
+        self.version = Some(version);
+
+        true
+    }
+
+    pub fn added(&self) -> Option<u64> {
+        self.added
+    }
+
+    pub fn set_added(&mut self, added: u64) {
+        self.added = Some(added);
     }
 
     pub fn priority(&self) -> i32 {
@@ -47,10 +199,158 @@ impl Alternative {
     pub fn target(&self) -> &std::path::Path {
         &self.target
     }
+
+    pub fn kind(&self) -> &LinkKind {
+        &self.kind
+    }
+
+    // the contents of the script to place at the link path, if this
+    // alternative is a wrapper rather than a symlink
+    pub fn wrapper_script(&self) -> Option<String> {
+        let (args, env) = match self.kind {
+            LinkKind::Symlink => return None,
+            LinkKind::Wrapper{ ref args, ref env } => (args, env),
+        };
+
+        let mut script = format!("#!/bin/sh\n{}\n", WRAPPER_MARKER);
+
+        for (name, value) in env.iter() {
+            script += &format!("{}={}\nexport {}\n", name, quote(value), name);
+        }
+
+        script += "exec ";
+        script += &quote(&self.target.to_string_lossy());
+
+        for arg in args.iter() {
+            script += " ";
+            script += &quote(arg);
+        }
+
+        script += " \"$@\"\n";
+
+        Some(script)
+    }
+
+    pub fn followers(&self) -> &Followers {
+        &self.followers
+    }
+
+    pub fn into_followers(self) -> Followers {
+        self.followers
+    }
+
+    pub fn source(&self) -> Option<&std::path::Path> {
+        self.source.as_deref()
+    }
+
+    pub fn owners(&self) -> &[String] {
+        &self.owners
+    }
+
+    pub fn is_owned_by(&self, owner: &str) -> bool {
+        self.owners.iter().any(|o| o == owner)
+    }
+
+    pub fn add_owner(&mut self, owner: &str) -> bool {
+        if self.is_owned_by(owner) {
+            return false;
+        }
+
+        self.owners.push(owner.to_string());
+
+        true
+    }
+
+    // whether something other than a drop-in holds a reference to this
+    // alternative, either an owner or someone who added it by hand
+    pub fn is_referenced(&self) -> bool {
+        !self.owners.is_empty() || self.added_by_hand
+    }
+
+    fn is_unowned(&self) -> bool {
+        self.owners.is_empty() && self.source.is_none()
+    }
+
+    // takes on the owners and registration time of from, which this
+    // alternative replaces. either of them having been added without an
+    // owner counts as a reference of its own
+    pub fn inherit(&mut self, from: &Alternative) {
+        let by_hand = from.added_by_hand || from.is_unowned()
+            || self.is_unowned();
+        let added = std::mem::replace(&mut self.owners, from.owners.clone());
+
+        for owner in added.iter() {
+            self.add_owner(owner);
+        }
+
+        self.added_by_hand = by_hand && !self.owners.is_empty();
+
+        self.added = from.added;
+    }
+
+    pub fn remove_owner(&mut self, owner: &str) -> bool {
+        match self.owners.iter().position(|o| o == owner) {
+            Some(i) => {
+                self.owners.remove(i);
+
+                true
+            },
+            None => false,
+        }
+    }
 }
 
 impl std::fmt::Display for Alternative {
     fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(formatter, "{}: {}", self.target.display(), self.priority)
+        write!(formatter, "{}: {}", self.target.display(), self.priority)?;
+
+        if let Some(ref version) = self.version {
+            write!(formatter, " (version {})", version)?;
+        }
+
+        if let LinkKind::Wrapper{ ref args, ref env } = self.kind {
+            let mut settings: Vec<String> = env.iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect();
+            settings.extend(args.iter().cloned());
+
+            if settings.is_empty() {
+                write!(formatter, " (wrapper)")?;
+            } else {
+                write!(formatter, " (wrapper: {})", settings.join(" "))?;
+            }
+        }
+
+        if !self.tags.is_empty() {
+            write!(formatter, " (tagged {})", self.tags.join(", "))?;
+        }
+
+        if !self.conditions.is_empty() {
+            let conditions: Vec<String> = self.conditions.iter()
+                .map(Condition::to_string)
+                .collect();
+
+            write!(formatter, " (if {})", conditions.join(", "))?;
+        }
+
+        if !self.owners.is_empty() {
+            write!(formatter, " (owned by {}", self.owners.join(", "))?;
+
+            if self.added_by_hand {
+                write!(formatter, " and added by hand")?;
+            }
+
+            write!(formatter, ")")?;
+        }
+
+        if let Some(ref source) = self.source {
+            write!(formatter, " (from {})", source.display())?;
+        }
+
+        Ok(())
     }
 }
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
@@ -29,13 +29,26 @@ extern crate std;
 extern crate serde_json;
 
 use super::alternative::Alternative;
-use super::alternative_list::AlternativeList;
+use super::alternative_list::{AlternativeList, ChainedTargets};
+use super::clock::{Clock, SystemClock};
+use super::constraint::Constraint;
+use super::drop_in;
 use super::filesystem;
+use super::settings::Settings;
 
-type AlternativeTable = std::collections::HashMap<String, AlternativeList>;
+static SETTINGS_FILE: &str = ".settings.json";
+
+// the most groups that a chain of links may pass through, such as c++
+// linking to the link of cc
+static MAX_CHAIN_LENGTH: usize = 8;
+
+type AlternativeTable = std::collections::BTreeMap<String, AlternativeList>;
 
 pub struct AlternativeDb {
     table: AlternativeTable,
+    settings: Settings,
+    root: Option<std::path::PathBuf>,
+    clock: std::rc::Rc<dyn Clock>,
 }
 
 impl AlternativeDb {
@@ -46,15 +59,21 @@ impl AlternativeDb {
             Ok(c) => c,
             Err(e) => {
                 if e.kind() == std::io::ErrorKind::NotFound {
-                    return Ok(AlternativeDb{ table: AlternativeTable::new() });
+                    return Ok(AlternativeDb{ table: AlternativeTable::new(),
+                                             settings: Settings::default(),
+                                             root: None,
+                                             clock: std::rc::Rc::new(
+                                                 SystemClock
+                                             ) });
                 }
 
                 return Err(e);
             },
         };
 
-        let to_reserve = estimate_size(&children);
-        let mut table = AlternativeTable::with_capacity(to_reserve);
+        let clock: std::rc::Rc<dyn Clock> = std::rc::Rc::new(SystemClock);
+        let settings = AlternativeDb::read_settings(folder_path)?;
+        let mut table = AlternativeTable::new();
 
         for child in children {
             let entry = match child {
@@ -69,6 +88,10 @@ impl AlternativeDb {
 
             let path = entry.path();
 
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+
             let name = String::from(match path.file_stem() {
                 Some(s) => s.to_string_lossy(),
                 None => {
@@ -79,32 +102,212 @@ impl AlternativeDb {
                 },
             });
 
-            let contents = match filesystem::read(&path) {
-                Ok(c) => c,
+            let mut list = match AlternativeDb::read_list(&path) {
+                Ok(l) => l,
                 Err(e) => {
-                    eprintln!("update-alternatives: could not read file {}: {}",
+                    eprintln!("update-alternatives: could not load {}: {}",
                               path.display(), e);
 
                     continue;
                 }
             };
 
-            let list: AlternativeList = match serde_json::from_str(&contents) {
-                Ok(l) => l,
-                Err(e) => {
-                    eprintln!("update-alternatives: unable to \
-                             deserialize {}: {}", path.display(), e);
+            eprintln!("update-alternatives: loading alternative for {} with \
+                      {} entries...", name, list.num_links());
+            list.inherit(&settings, None, &clock);
+            table.insert(name, list);
+        }
 
-                    continue;
-                }
+        let mut db = AlternativeDb{ table, settings, root: None, clock };
+
+        db.link_chains();
+
+        Ok(db)
+    }
+
+    fn read_settings(folder: &std::path::Path) -> std::io::Result<Settings> {
+        let contents = match filesystem::read(folder.join(SETTINGS_FILE)) {
+            Ok(c) => c,
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Settings::default());
+            },
+            Err(e) => return Err(e),
+        };
+
+        serde_json::from_str(&contents).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+        })
+    }
+
+    pub fn read_list<P: std::convert::AsRef<std::path::Path>>(path: P)
+        -> std::io::Result<AlternativeList> {
+        let contents = filesystem::read(path)?;
+
+        serde_json::from_str(&contents).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+        })
+    }
+
+    // finds the group dispatched by a shim whose link is named file_name,
+    // reading each group quietly and applying the global settings to it
+    pub fn find_shim<P: std::convert::AsRef<std::path::Path>>(
+        folder: P, file_name: &std::ffi::OsStr
+    ) -> std::io::Result<Option<(String, AlternativeList)>> {
+        let folder_path = folder.as_ref();
+        let settings = AlternativeDb::read_settings(folder_path)?;
+        let clock: std::rc::Rc<dyn Clock> = std::rc::Rc::new(SystemClock);
+
+        for child in folder_path.read_dir()? {
+            let path = child?.path();
+
+            let name = match path.file_stem() {
+                Some(s) if !s.to_string_lossy().starts_with('.') => {
+                    s.to_string_lossy().into_owned()
+                },
+                _ => continue,
             };
 
-            println!("update-alternatives: loading alternative for {} with {} \
-                     entries...", name, list.num_links());
-            table.insert(name, list);
+            let mut list = match AlternativeDb::read_list(&path) {
+                Ok(l) => l,
+                Err(_) => continue,
+            };
+
+            if !list.is_shim() || list.path().file_name() != Some(file_name) {
+                continue;
+            }
+
+            list.inherit(&settings, None, &clock);
+
+            return Ok(Some((name, list)));
+        }
+
+        Ok(None)
+    }
+
+    // changes the global settings, passing them on to every group
+    pub fn configure<F: FnOnce(&mut Settings) -> bool>(&mut self, change: F)
+    -> bool {
+        if !change(&mut self.settings) {
+            return false;
+        }
+
+        self.inherit();
+
+        true
+    }
+
+    pub fn root(&self) -> Option<&std::path::Path> {
+        self.root.as_deref()
+    }
+
+    // manages the tree mounted at root rather than the running system, so
+    // that links are placed under root but still point to paths as seen
+    // from inside it
+    pub fn set_root(&mut self, root: Option<std::path::PathBuf>) {
+        self.root = root;
+        self.inherit();
+    }
+
+    // evaluates time-limited selections against clock instead of the time
+    // of the running system
+    pub fn set_clock(&mut self, clock: std::rc::Rc<dyn Clock>) {
+        self.clock = clock;
+        self.inherit();
+    }
+
+    pub fn now(&self) -> u64 {
+        self.clock.now()
+    }
+
+    fn inherit(&mut self) {
+        for list in self.table.values_mut() {
+            list.inherit(&self.settings, self.root.as_deref(), &self.clock);
+        }
+
+        self.link_chains();
+    }
+
+    // tells every group what the link of each group resolves to, following
+    // selections until they reach a target that is not a link, so that the
+    // links themselves are never followed on disk. groups in a cycle resolve
+    // to nothing. call this again after changing selections through
+    // alternatives_mut
+    pub fn link_chains(&mut self) {
+        let mut chained: ChainedTargets = self.table
+            .values()
+            .map(|l| (l.path().to_path_buf(), None))
+            .collect();
+
+        for _ in 0..MAX_CHAIN_LENGTH {
+            for list in self.table.values_mut() {
+                list.set_chained(chained.clone());
+            }
+
+            let next: ChainedTargets = self.table
+                .values()
+                .map(|l| {
+                    let resolved = l.select().and_then(|a| {
+                        match chained.get(a.target()) {
+                            Some(r) => r.clone(),
+                            None => Some(a.target().to_path_buf()),
+                        }
+                    });
+
+                    (l.path().to_path_buf(), resolved)
+                })
+                .collect();
+
+            if next == chained {
+                return;
+            }
+
+            chained = next;
+        }
+
+        for list in self.table.values_mut() {
+            list.set_chained(chained.clone());
+        }
+    }
+
+    // reverts every selection made by hand whose time is up, returning
+    // whether any was
+    pub fn expire(&mut self) -> bool {
+        let mut changed = false;
+
+        for (name, list) in self.table.iter_mut() {
+            match list.expire() {
+                Some(Some(previous)) => {
+                    eprintln!("update-alternatives: the selection for {} \
+                              expired, reverting to {}", name,
+                              previous.display());
+                },
+                Some(None) => {
+                    eprintln!("update-alternatives: the selection for {} \
+                              expired, reverting to automatic selection", name);
+                },
+                None => continue,
+            }
+
+            changed = true;
         }
 
-        Ok(AlternativeDb{ table })
+        if changed {
+            self.link_chains();
+        }
+
+        changed
+    }
+
+    // the earliest time at which a selection made by hand expires
+    pub fn next_expiry(&self) -> Option<u64> {
+        self.table.values().filter_map(AlternativeList::expires_at).min()
+    }
+
+    // changes the links of held groups as if they were not held
+    pub fn ignore_holds(&mut self) {
+        for list in self.table.values_mut() {
+            list.ignore_hold();
+        }
     }
 
     pub fn num_alternatives(&self) -> usize {
@@ -123,17 +326,219 @@ impl AlternativeDb {
         Some(&self.table[name])
     }
 
+    pub fn alternatives_mut(&mut self, name: &str)
+        -> Option<&mut AlternativeList> {
+        self.table.get_mut(name)
+    }
+
+    pub fn lists(&self) -> impl Iterator<Item = (&str, &AlternativeList)> {
+        self.table.iter().map(|(name, list)| (name.as_str(), list))
+    }
+
+    pub fn lists_mut(&mut self)
+        -> impl Iterator<Item = (&str, &mut AlternativeList)> {
+        self.table.iter_mut().map(|(name, list)| (name.as_str(), list))
+    }
+
+    // creates the group name with its link at path, which must not be the
+    // link of another group
+    pub fn add_group<P: std::convert::AsRef<std::path::Path>>(
+        &mut self, name: &str, path: P
+    ) -> std::io::Result<()> {
+        let link = path.as_ref();
+
+        AlternativeDb::check_name(name)?;
+        self.check_link(name, link)?;
+
+        let mut list = AlternativeList::new(link);
+
+        list.inherit(&self.settings, self.root.as_deref(), &self.clock);
+        self.table.insert(name.to_string(), list);
+
+        Ok(())
+    }
+
+    // moves the link of the group name to path. the link itself is moved
+    // when the links are next written
+    pub fn relocate<P: std::convert::AsRef<std::path::Path>>(
+        &mut self, name: &str, path: P
+    ) -> std::io::Result<bool> {
+        let link = path.as_ref();
+
+        let held = self.table
+            .get(name)
+            .and_then(|l| l.active_hold().map(|h| (l.path(), h)));
+
+        if let Some((current, hold)) = held {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!("{} is {}; pass --ignore-holds to move it anyway",
+                        current.display(), hold)
+            ));
+        }
+
+        self.check_link(name, link)?;
+        self.check_chains(name, link, None).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, e)
+        })?;
+
+        let relocated = match self.table.get_mut(name) {
+            Some(l) => l.relocate(link),
+            None => return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("no alternatives found for {}", name)
+            )),
+        };
+
+        self.link_chains();
+
+        Ok(relocated)
+    }
+
+    // group names become the file names of their records and of their
+    // default links, so they must not reach outside of those directories
+    fn check_name(name: &str) -> std::io::Result<()> {
+        if !filesystem::is_file_name(name) {
+            return Err(filesystem::invalid(
+                format!("{:?} is not a valid group name", name)
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn check_link(&self, name: &str,
+                  link: &std::path::Path) -> std::io::Result<()> {
+        if !link.is_absolute() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{} is not an absolute path", link.display())
+            ));
+        }
+
+        let claimed_by = self.lists()
+            .find(|&(n, l)| n != name && l.path() == link)
+            .map(|(n, _)| n);
+
+        if let Some(other) = claimed_by {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("{} is already the link for {}", link.display(), other)
+            ));
+        }
+
+        Ok(())
+    }
+
     pub fn add_alternative(&mut self, name: &str,
-                           to_add: Alternative) -> bool {
-        if !self.has_alternatives(&name) {
-            let path = format!("/usr/local/bin/{}", name);
+                           to_add: Alternative) -> std::io::Result<bool> {
+        AlternativeDb::check_name(name)?;
+
+        let link = match self.table.get(name) {
+            Some(l) => l.path().to_path_buf(),
+            None => std::path::PathBuf::from(format!("/usr/local/bin/{}",
+                                                     name)),
+        };
+
+        self.check_chains(name, &link, Some(to_add.target())).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, e)
+        })?;
 
-            self.table.insert(name.to_string(), AlternativeList::new(path));
+        if !self.has_alternatives(name) {
+            self.add_group(name, link)?;
         }
 
-        let list = self.table.get_mut(name).unwrap();
+        let added = self.table.get_mut(name).unwrap().add_alternative(to_add);
+
+        self.link_chains();
+
+        Ok(added)
+    }
+
+    // the group whose link is at path, along with its name
+    pub fn group_at(&self, path: &std::path::Path)
+    -> Option<(&str, &AlternativeList)> {
+        self.table
+            .iter()
+            .find(|&(_, l)| l.path() == path)
+            .map(|(n, l)| (n.as_str(), l))
+    }
+
+    // follows the selection for name through the links of the groups that
+    // it selects, returning the link of each group and finally the target
+    // that is not the link of any group. the last entry is a link if its
+    // group selects nothing
+    pub fn chain(&self, name: &str)
+    -> Result<Vec<std::path::PathBuf>, String> {
+        let mut list = match self.table.get(name) {
+            Some(l) => l,
+            None => return Err(format!("no alternatives found for {}", name)),
+        };
+        let mut names = vec![name];
+        let mut chain = vec![list.path().to_path_buf()];
+
+        while let Some(selected) = list.select() {
+            chain.push(selected.target().to_path_buf());
+
+            let (next_name, next) = match self.group_at(selected.target()) {
+                Some(g) => g,
+                None => break,
+            };
+
+            if names.contains(&next_name) {
+                names.push(next_name);
+
+                return Err(format!("{} form a cycle", names.join(" -> ")));
+            }
+
+            names.push(next_name);
 
-        list.add_alternative(to_add)
+            if names.len() > MAX_CHAIN_LENGTH {
+                return Err(format!("{} chain more than {} groups",
+                                   names.join(" -> "), MAX_CHAIN_LENGTH));
+            }
+
+            list = next;
+        }
+
+        Ok(chain)
+    }
+
+    // checks that placing the link of name at link, and adding an
+    // alternative for it that points to target if one is given, would
+    // neither chain groups into a cycle nor chain more than
+    // MAX_CHAIN_LENGTH groups together through any of their alternatives
+    pub fn check_chains(&self, name: &str, link: &std::path::Path,
+                        target: Option<&std::path::Path>)
+    -> Result<(), String> {
+        let mut links: std::collections::BTreeMap<&str, &std::path::Path> =
+            self.table.iter().map(|(n, l)| (n.as_str(), l.path())).collect();
+        links.insert(name, link);
+
+        let owner = |path: &std::path::Path| {
+            links.iter().find(|&(_, l)| *l == path).map(|(n, _)| *n)
+        };
+
+        let mut edges: std::collections::BTreeMap<&str, Vec<&str>> =
+            std::collections::BTreeMap::new();
+
+        for (list_name, list) in self.table.iter() {
+            for alternative in list.alternatives() {
+                if let Some(o) = owner(alternative.target()) {
+                    edges.entry(list_name).or_default().push(o);
+                }
+            }
+        }
+
+        if let Some(o) = target.and_then(owner) {
+            edges.entry(name).or_default().push(o);
+        }
+
+        for start in links.keys() {
+            walk_chains(&edges, &mut vec![*start])?;
+        }
+
+        Ok(())
     }
 
     pub fn remove_alternative<P: std::convert::AsRef<std::path::Path>>(
@@ -143,9 +548,198 @@ impl AlternativeDb {
             return false;
         }
 
-        let list = self.table.get_mut(name).unwrap();
+        let removed = self.table
+            .get_mut(name)
+            .unwrap()
+            .remove_alternative(target);
+
+        self.link_chains();
+
+        removed
+    }
+
+    pub fn rederive_priorities(&mut self) -> bool {
+        let mut changed = false;
+
+        for (name, list) in self.table.iter_mut() {
+            for alternative in list.rederive_priorities() {
+                println!("update-alternatives: alternative {} for {} is now \
+                         version {} with priority {}",
+                         alternative.target().display(), name,
+                         alternative.version().unwrap(),
+                         alternative.priority());
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    // the constraints that the current selections violate, along with the
+    // name of the group declaring each
+    pub fn violations(&self) -> Vec<(&str, &Constraint)> {
+        let mut violations = Vec::new();
+
+        for (name, list) in self.table.iter() {
+            let selected = list.select();
+
+            for constraint in list.constraints() {
+                if !constraint.applies(selected) {
+                    continue;
+                }
+
+                let required = self.table
+                    .get(constraint.requires())
+                    .and_then(AlternativeList::select);
+
+                if !constraint.is_met_by(required) {
+                    violations.push((name.as_str(), constraint));
+                }
+            }
+        }
+
+        violations
+    }
+
+    // selects a target by hand in each group required by a violated
+    // constraint, so that the constraint holds. as this may in turn violate
+    // the constraints of those groups, it is repeated until no more can be
+    // resolved. returns whether any selection was changed
+    pub fn resolve_constraints(&mut self) -> bool {
+        let mut changed = false;
+
+        for _ in 0..=self.table.len() {
+            let violations: Vec<Constraint> = self.violations()
+                .into_iter()
+                .map(|(_, c)| c.clone())
+                .collect();
+            let mut progressed = false;
+
+            for constraint in violations.iter() {
+                let list = match self.table.get_mut(constraint.requires()) {
+                    Some(l) => l,
+                    None => continue,
+                };
+
+                let target = match list.select_under(constraint.under()) {
+                    Some(a) => a.target().to_path_buf(),
+                    None => continue,
+                };
+
+                if list.set_manual(Some(target.clone())) {
+                    println!("update-alternatives: {} now uses {}",
+                             constraint.requires(), target.display());
+                    progressed = true;
+                }
+            }
+
+            if !progressed {
+                break;
+            }
+
+            changed = true;
+        }
+
+        changed
+    }
+
+    pub fn remove_owner(&mut self, owner: &str, name: Option<&str>,
+                        target: Option<&std::path::Path>) -> bool {
+        let mut changed = false;
+
+        for (list_name, list) in self.table.iter_mut() {
+            if name.is_some_and(|n| n != list_name) {
+                continue;
+            }
+
+            for (released, removed) in list.remove_owner(owner, target) {
+                if removed {
+                    println!("update-alternatives: removed alternative {} for \
+                             {}, as {} was its last owner", released.display(),
+                             list_name, owner);
+                } else {
+                    println!("update-alternatives: released {}'s reference to \
+                             alternative {} for {}", owner, released.display(),
+                             list_name);
+                }
+
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    pub fn sync<P: std::convert::AsRef<std::path::Path>>(&mut self, folder: P)
+        -> std::io::Result<bool> {
+        // sources are recorded with the directory canonicalized, so compare
+        // against the same form; a directory that is gone cannot be resolved
+        let folder_path = folder.as_ref()
+            .canonicalize()
+            .unwrap_or_else(|_| folder.as_ref().to_path_buf());
+        let (drop_ins, unreadable) = drop_in::read_folder(&folder_path)?;
+        let mut declared = std::collections::HashSet::new();
+        let mut changed = false;
+
+        for (source, drop_in) in drop_ins {
+            let name = drop_in.name().to_string();
+            let to_add = drop_in.into_alternative(&source);
+            let target = to_add.target().to_path_buf();
+            let priority = to_add.priority();
+
+            declared.insert((name.clone(), target.clone(), source.clone()));
+
+            let added_by_hand = self.alternatives(&name)
+                .and_then(|l| l.alternative(&target))
+                .is_some_and(|a| a.source().is_none());
+
+            if added_by_hand {
+                println!("update-alternatives: leaving alternative {} for {} \
+                         alone, as it was not added by a drop-in",
+                         target.display(), name);
+
+                continue;
+            }
+
+            match self.add_alternative(&name, to_add) {
+                Ok(true) => {
+                    println!("update-alternatives: added alternative {} for {} \
+                             with priority {} from {}", target.display(),
+                             name, priority, source.display());
+                    changed = true;
+                },
+                Ok(false) => (),
+                Err(e) => {
+                    eprintln!("update-alternatives: skipping {}: {}",
+                              source.display(), e);
+                },
+            }
+        }
+
+        for (name, list) in self.table.iter_mut() {
+            let stale: Vec<std::path::PathBuf> = list.alternatives()
+                .iter()
+                .filter(|a| match a.source() {
+                    Some(s) => s.parent() == Some(&folder_path)
+                        && !unreadable.iter().any(|u| u == s)
+                        && !declared.contains(&(name.clone(),
+                                                a.target().to_path_buf(),
+                                                s.to_path_buf())),
+                    None => false,
+                })
+                .map(|a| a.target().to_path_buf())
+                .collect();
 
-        list.remove_alternative(target)
+            for target in stale {
+                list.remove_alternative(&target);
+
+                println!("update-alternatives: removed alternative {} for {}, \
+                         as its drop-in is gone", target.display(), name);
+                changed = true;
+            }
+        }
+
+        Ok(changed)
     }
 
     pub fn write_out<P: std::convert::AsRef<std::path::Path>>(&self, folder: P)
@@ -153,9 +747,7 @@ impl AlternativeDb {
         let folder_path = folder.as_ref();
 
         if !folder_path.exists() {
-            if let Err(e) = filesystem::create_dir(folder_path) {
-                return Err(e);
-            }
+            filesystem::create_dir(folder_path)?;
         } else if !folder_path.is_dir() {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::AlreadyExists, "path is not a directory"
@@ -163,6 +755,14 @@ impl AlternativeDb {
         }
 
         let mut written: usize = 0;
+        let settings_file = folder_path.join(SETTINGS_FILE);
+
+        if !self.settings.is_default() || settings_file.exists() {
+            let to_write = serde_json::to_string(&self.settings)
+                .map_err(std::io::Error::from)?;
+
+            written += filesystem::write(to_write, &settings_file)?;
+        }
 
         for (name, list) in self.table.iter() {
             let db_file = folder_path.join(name).with_extension("json");
@@ -180,9 +780,7 @@ impl AlternativeDb {
                     AlternativeDb::cleanup(&db_file);
                 },
                 Err(e) => {
-                    if let Err(e) = AlternativeDb::recover(&db_file) {
-                        return Err(e);
-                    }
+                    AlternativeDb::recover(&db_file)?;
 
                     return Err(e);
                 },
@@ -192,14 +790,24 @@ impl AlternativeDb {
         Ok(written)
     }
 
-    pub fn write_links(&self) -> std::io::Result<()> {
-        for list in self.table.values() {
-            if let Err(e) = list.make_symlink() {
-                return Err(e);
+    // returns whether the database has to be written out again, as the
+    // hashes recorded for hardlinks and copies changed or a link was moved
+    pub fn write_links(&mut self) -> std::io::Result<bool> {
+        let mut record_changed = false;
+
+        self.link_chains();
+
+        for list in self.table.values_mut() {
+            let result = list.make_symlink();
+
+            if list.take_record_changed() {
+                record_changed = true;
             }
+
+            result?;
         }
 
-        Ok(())
+        Ok(record_changed)
     }
 
     fn rename_existing(link: &std::path::Path) -> std::io::Result<()> {
@@ -223,7 +831,7 @@ impl AlternativeDb {
     }
 
     fn cleanup(link: &std::path::Path) {
-        if let Err(e) = AlternativeDb::remove_renamed(&link) {
+        if let Err(e) = AlternativeDb::remove_renamed(link) {
             eprintln!("update-alternatives: could not remove {}.old: {}",
                       link.display(), e);
         }
@@ -257,11 +865,301 @@ impl AlternativeDb {
     }
 }
 
-fn estimate_size<I: std::iter::Iterator>(iter: &I) -> usize {
-    let (lower_bound, upper_bound) = iter.size_hint();
+// extends chain by every group that its last group has an alternative
+// linking to, failing on the first cycle or chain that is too long
+fn walk_chains<'a>(edges: &std::collections::BTreeMap<&'a str, Vec<&'a str>>,
+                   chain: &mut Vec<&'a str>) -> Result<(), String> {
+    let last = chain[chain.len() - 1];
+
+    for &next in edges.get(last).into_iter().flatten() {
+        let is_cycle = chain.contains(&next);
+        chain.push(next);
+
+        if is_cycle {
+            return Err(format!("{} would form a cycle", chain.join(" -> ")));
+        }
+
+        if chain.len() > MAX_CHAIN_LENGTH {
+            return Err(format!("{} would chain more than {} groups",
+                               chain.join(" -> "), MAX_CHAIN_LENGTH));
+        }
+
+        walk_chains(edges, chain)?;
+        chain.pop();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::clock::FixedClock;
+    use super::super::hold::Hold;
+
+    fn empty_db() -> AlternativeDb {
+        AlternativeDb::from_folder("/nonexistent/update-alternatives-test")
+            .unwrap()
+    }
+
+    fn set_time(db: &mut AlternativeDb, now: u64) {
+        db.set_clock(std::rc::Rc::new(FixedClock(now)));
+    }
+
+    fn add(db: &mut AlternativeDb, name: &str, target: &str) {
+        db.add_alternative(name, Alternative::from_parts(target, 10)).unwrap();
+    }
+
+    fn chains(db: &AlternativeDb, name: &str, link: &str,
+              target: Option<&str>) -> Result<(), String> {
+        db.check_chains(name, std::path::Path::new(link),
+                        target.map(std::path::Path::new))
+    }
+
+    fn write_drop_in(folder: &std::path::Path, file: &str, name: &str,
+                     target: &str) {
+        filesystem::write(format!(
+            "{{\"name\": {:?}, \"target\": {:?}, \"priority\": 10}}",
+            name, target
+        ), folder.join(file)).unwrap();
+    }
+
+    fn source(db: &AlternativeDb, target: &str)
+    -> Option<Option<std::path::PathBuf>> {
+        db.alternatives("cc")
+            .and_then(|l| l.alternative(std::path::Path::new(target)))
+            .map(|a| a.source().map(std::path::Path::to_path_buf))
+    }
+
+    #[test]
+    fn syncs_drop_ins() {
+        let scratch = filesystem::ScratchDir::new("sync");
+        let folder = scratch.path().canonicalize().unwrap();
+        let mut db = empty_db();
+        add(&mut db, "cc", "/usr/bin/tcc");
+        add(&mut db, "cc", "/usr/bin/clang");
+        write_drop_in(&folder, "gcc.json", "cc", "/usr/bin/gcc");
+        write_drop_in(&folder, "clang.json", "cc", "/usr/bin/clang");
+
+        assert!(db.sync(&folder).unwrap());
+        assert_eq!(source(&db, "/usr/bin/gcc"),
+                   Some(Some(folder.join("gcc.json"))));
+        assert_eq!(source(&db, "/usr/bin/clang"), Some(None));
+
+        std::fs::remove_file(folder.join("gcc.json")).unwrap();
+        std::fs::remove_file(folder.join("clang.json")).unwrap();
+
+        assert!(db.sync(&folder).unwrap());
+        assert_eq!(source(&db, "/usr/bin/gcc"), None);
+        assert_eq!(source(&db, "/usr/bin/clang"), Some(None));
+        assert_eq!(source(&db, "/usr/bin/tcc"), Some(None));
+        assert!(!db.sync(&folder).unwrap());
+    }
+
+    #[test]
+    fn rejects_group_names_that_are_not_file_names() {
+        let scratch = filesystem::ScratchDir::new("sync-names");
+        let mut db = empty_db();
+        write_drop_in(scratch.path(), "escape.json", "../../../escaped",
+                      "/usr/bin/gcc");
+
+        assert!(!db.sync(scratch.path()).unwrap());
+        assert_eq!(db.num_alternatives(), 0);
+
+        for name in ["", ".", "..", "../escaped", "bin/cc"].iter() {
+            assert!(db.add_alternative(name, Alternative::from_parts(
+                "/usr/bin/gcc", 10
+            )).is_err());
+            assert!(db.add_group(name, "/opt/cc").is_err());
+        }
+    }
+
+    // a database under root with java, javac and jar each provided by two
+    // jdks, where jdk21 is preferred for all but java
+    fn jdks(root: &std::path::Path) -> AlternativeDb {
+        let mut db = empty_db();
+        db.set_root(Some(root.to_path_buf()));
+
+        for &(jdk, java, tools) in [("jdk17", 20, 10),
+                                    ("jdk21", 10, 20)].iter() {
+            for &(name, priority) in [("java", java), ("javac", tools),
+                                      ("jar", tools)].iter() {
+                let target = format!("/opt/{}/bin/{}", jdk, name);
+                let on_disk = filesystem::under_root(Some(root), &target);
+                std::fs::create_dir_all(on_disk.parent().unwrap()).unwrap();
+                filesystem::write_script("#!/bin/sh\n", &on_disk).unwrap();
+
+                db.add_alternative(name, Alternative::from_parts(&target,
+                                                                 priority))
+                    .unwrap();
+            }
+        }
+
+        db
+    }
+
+    fn selected<'a>(db: &'a AlternativeDb, name: &str)
+    -> Option<&'a std::path::Path> {
+        db.alternatives(name)?.select().map(Alternative::target)
+    }
+
+    #[test]
+    fn detects_violated_constraints() {
+        let scratch = filesystem::ScratchDir::new("constraints");
+        let mut db = jdks(scratch.path());
+        db.alternatives_mut("java").unwrap()
+            .add_constraint(Constraint::new("/opt/jdk17", "javac",
+                                            "/opt/jdk17"));
+
+        let violations = db.violations();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].0, "java");
+        assert_eq!(violations[0].1.requires(), "javac");
+
+        db.alternatives_mut("java").unwrap()
+            .set_manual(Some(std::path::PathBuf::from("/opt/jdk21/bin/java")));
+        assert!(db.violations().is_empty());
+    }
+
+    #[test]
+    fn resolves_constraints_to_a_fixed_point() {
+        let scratch = filesystem::ScratchDir::new("constraints-resolve");
+        let mut db = jdks(scratch.path());
+        db.alternatives_mut("java").unwrap()
+            .add_constraint(Constraint::new("/opt/jdk17", "javac",
+                                            "/opt/jdk17"));
+        db.alternatives_mut("javac").unwrap()
+            .add_constraint(Constraint::new("/opt/jdk17", "jar",
+                                            "/opt/jdk17"));
+
+        assert!(db.resolve_constraints());
+        assert!(db.violations().is_empty());
+        assert_eq!(selected(&db, "javac"),
+                   Some(std::path::Path::new("/opt/jdk17/bin/javac")));
+        assert_eq!(selected(&db, "jar"),
+                   Some(std::path::Path::new("/opt/jdk17/bin/jar")));
+        assert!(!db.resolve_constraints());
+    }
+
+    #[test]
+    fn sync_leaves_held_links_unchanged() {
+        let scratch = filesystem::ScratchDir::new("hold-sync");
+        let root = scratch.path();
+        let folder = root.join("drop-ins");
+        let mut db = empty_db();
+        db.set_root(Some(root.to_path_buf()));
+
+        for directory in ["opt", "usr/local/bin", "drop-ins"].iter() {
+            std::fs::create_dir_all(root.join(directory)).unwrap();
+        }
+
+        for target in ["opt/gcc", "opt/clang"].iter() {
+            filesystem::write_script("#!/bin/sh\n", root.join(target))
+                .unwrap();
+        }
+
+        add(&mut db, "cc", "/opt/gcc");
+        db.write_links().unwrap();
+        db.alternatives_mut("cc").unwrap().set_hold(Some(Hold::new(
+            Some(std::path::Path::new("/opt/gcc")), None, 0
+        )));
+        write_drop_in(&folder, "clang.json", "cc", "/opt/clang");
+
+        assert!(db.sync(&folder).unwrap());
+        db.write_links().unwrap();
+        assert_eq!(root.join("usr/local/bin/cc").read_link().unwrap(),
+                   std::path::Path::new("/opt/gcc"));
+    }
+
+    #[test]
+    fn refuses_to_relocate_held_groups() {
+        let mut db = empty_db();
+        add(&mut db, "cc", "/usr/bin/gcc");
+        db.alternatives_mut("cc").unwrap().set_hold(Some(Hold::new(
+            Some(std::path::Path::new("/usr/bin/gcc")), None, 0
+        )));
+
+        let error = db.relocate("cc", "/opt/bin/cc").unwrap_err();
+        assert!(error.to_string().contains("--ignore-holds"), "{}", error);
+        assert_eq!(db.alternatives("cc").unwrap().path(),
+                   std::path::Path::new("/usr/local/bin/cc"));
+
+        db.ignore_holds();
+        assert!(db.relocate("cc", "/opt/bin/cc").unwrap());
+    }
+
+    #[test]
+    fn accepts_chains_without_cycles() {
+        let mut db = empty_db();
+        add(&mut db, "cc", "/usr/bin/gcc");
+
+        assert!(chains(&db, "c++", "/usr/local/bin/c++",
+                       Some("/usr/local/bin/cc")).is_ok());
+    }
+
+    #[test]
+    fn rejects_cycles_from_new_alternatives() {
+        let mut db = empty_db();
+        add(&mut db, "a", "/opt/gcc");
+        add(&mut db, "b", "/usr/local/bin/a");
+
+        let error = chains(&db, "a", "/usr/local/bin/a",
+                           Some("/usr/local/bin/b")).unwrap_err();
+        assert!(error.contains("would form a cycle"), "{}", error);
+        assert!(db.add_alternative("a", Alternative::from_parts(
+            "/usr/local/bin/b", 5
+        )).is_err());
+    }
+
+    #[test]
+    fn rejects_cycles_from_relocated_links() {
+        let mut db = empty_db();
+        add(&mut db, "a", "/opt/gcc");
+        add(&mut db, "b", "/usr/local/bin/a");
+        add(&mut db, "a", "/opt/x/b");
+
+        assert!(chains(&db, "b", "/opt/x/b", None).is_err());
+        assert!(db.relocate("b", "/opt/x/b").is_err());
+        assert!(db.relocate("b", "/opt/x/c").is_ok());
+    }
+
+    #[test]
+    fn rejects_long_chains() {
+        let mut db = empty_db();
+        add(&mut db, "g0", "/opt/gcc");
+
+        for i in 1..MAX_CHAIN_LENGTH {
+            add(&mut db, &format!("g{}", i),
+                &format!("/usr/local/bin/g{}", i - 1));
+        }
+
+        let error = chains(&db, "g8", "/usr/local/bin/g8",
+                           Some("/usr/local/bin/g7")).unwrap_err();
+        assert!(error.contains("more than 8 groups"), "{}", error);
+    }
+
+    #[test]
+    fn expires_selections_against_clock() {
+        let mut db = empty_db();
+        set_time(&mut db, 1000);
+
+        for &(target, priority) in [("/usr/bin/gcc", 10),
+                                    ("/usr/bin/clang", 5)].iter() {
+            db.add_alternative("cc", Alternative::from_parts(target, priority))
+                .unwrap();
+        }
+
+        db.alternatives_mut("cc")
+            .unwrap()
+            .set_manual_for(std::path::PathBuf::from("/usr/bin/clang"), 60);
+        assert_eq!(db.next_expiry(), Some(1060));
+
+        set_time(&mut db, 1059);
+        assert!(!db.expire());
 
-    match upper_bound {
-        Some(v) => v,
-        None => lower_bound,
+        set_time(&mut db, 1060);
+        assert!(db.expire());
+        assert_eq!(db.next_expiry(), None);
+        assert!(!db.expire());
     }
 }
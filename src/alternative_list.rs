@@ -28,71 +28,1059 @@ extern crate std;
 
 extern crate serde_json;
 
-use super::alternative::Alternative;
-use super::filesystem;
+use super::alternative::{Alternative, Followers};
+use super::clock::{self, Clock, SystemClock};
+use super::constraint::Constraint;
+use super::filesystem::{self, LinkStrategy};
+use super::hold::Hold;
+use super::selection::{self, Policy, SelectionPolicy};
+use super::settings::{Settings, TiePolicy};
+use super::validate;
+
+// the content hash of each hardlink or copy that was placed, so that it can
+// be told apart from a file that update-alternatives did not create
+type Hashes = std::collections::BTreeMap<std::path::PathBuf, String>;
+
+// what a group switches between: a link to an executable, a link to a
+// directory, or the value of an environment variable exported by the
+// generated profile scripts
+#[derive(Serialize, Deserialize, PartialEq, Clone, Default)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum GroupKind {
+    #[default]
+    Link,
+    Directory,
+    Variable { name: String },
+}
+
+impl GroupKind {
+    pub fn is_link(&self) -> bool {
+        *self == GroupKind::Link
+    }
+}
+
+// when a selection made by hand for a limited time reverts, and what it
+// reverts to: the previous selection made by hand, or with None, selection
+// by policy
+#[derive(Serialize, Deserialize, PartialEq, Clone)]
+pub struct Expiry {
+    until: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous: Option<std::path::PathBuf>,
+}
+
+// what the link of each group finally resolves to, or None if it resolves to
+// nothing usable
+pub type ChainedTargets =
+    std::collections::BTreeMap<std::path::PathBuf, Option<std::path::PathBuf>>;
 
 #[derive(Serialize, Deserialize)]
 pub struct AlternativeList {
     path: std::path::PathBuf,
     links: Vec<Alternative>,
+    #[serde(default, skip_serializing_if = "GroupKind::is_link")]
+    kind: GroupKind,
+    #[serde(default, skip_serializing_if = "is_false")]
+    shim: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    link_strategy: Option<LinkStrategy>,
+    #[serde(default, skip_serializing_if = "Policy::is_priority")]
+    policy: Policy,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tie_policy: Option<TiePolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    preferred_tags: Option<Vec<String>>,
+    // the target selected by hand, which overrides the policy while it is
+    // eligible
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    manual: Option<std::path::PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expiry: Option<Expiry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    constraints: Vec<Constraint>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hold: Option<Hold>,
+    #[serde(default, skip_serializing_if = "Hashes::is_empty")]
+    hashes: Hashes,
+    // the link path before the group was relocated, kept until the link has
+    // been moved from it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous_path: Option<std::path::PathBuf>,
+    #[serde(skip)]
+    removed_followers: Followers,
+    #[serde(skip)]
+    record_changed: bool,
+    #[serde(skip)]
+    settings: Settings,
+    #[serde(skip)]
+    root: Option<std::path::PathBuf>,
+    #[serde(skip)]
+    custom_policy: Option<Box<dyn SelectionPolicy>>,
+    #[serde(skip)]
+    ignore_hold: bool,
+    #[serde(skip)]
+    clock: Option<std::rc::Rc<dyn Clock>>,
+    #[serde(skip)]
+    chained: ChainedTargets,
 }
 
 impl AlternativeList {
     pub fn new<P: std::convert::AsRef<std::path::Path>>(path: P)
         -> AlternativeList {
         AlternativeList{ path: std::path::PathBuf::from(path.as_ref()),
-                         links: Vec::new() }
+                         links: Vec::new(), kind: GroupKind::Link,
+                         shim: false,
+                         link_strategy: None, policy: Policy::Priority,
+                         tie_policy: None, preferred_tags: None,
+                         manual: None, expiry: None,
+                         constraints: Vec::new(), hold: None,
+                         hashes: Hashes::new(),
+                         removed_followers: Followers::new(),
+                         previous_path: None, record_changed: false,
+                         settings: Settings::default(),
+                         root: None, custom_policy: None,
+                         ignore_hold: false, clock: None,
+                         chained: ChainedTargets::new() }
+    }
+
+    // applies the global settings, the root and the clock of the database
+    pub fn inherit(&mut self, settings: &Settings,
+                   root: Option<&std::path::Path>,
+                   clock: &std::rc::Rc<dyn Clock>) {
+        self.settings = settings.clone();
+        self.root = root.map(std::path::Path::to_path_buf);
+        self.clock = Some(clock.clone());
+    }
+
+    // records what the links of groups resolve to, so that an alternative
+    // whose target is the link of another group is judged by what that group
+    // selects rather than by the link on disk
+    pub fn set_chained(&mut self, chained: ChainedTargets) {
+        self.chained = chained;
+    }
+
+    // the target that target resolves to through the links of other groups,
+    // which is target itself unless it is one of their links
+    pub fn resolved<'a>(&'a self, target: &'a std::path::Path)
+    -> Option<&'a std::path::Path> {
+        match self.chained.get(target) {
+            Some(r) => r.as_deref(),
+            None => Some(target),
+        }
+    }
+
+    fn now(&self) -> u64 {
+        match self.clock {
+            Some(ref c) => c.now(),
+            None => SystemClock.now(),
+        }
+    }
+
+    // where path is found on the host, which differs from path only when
+    // --root is set
+    pub fn on_disk(&self, path: &std::path::Path) -> std::path::PathBuf {
+        filesystem::under_root(self.root.as_deref(), path)
     }
 
     pub fn num_links(&self) -> usize {
         self.links.len()
     }
 
-    pub fn make_symlink(&self) -> std::io::Result<bool> {
-        let (target, priority) = match self.links
-                                           .iter()
-                                           .max_by_key(|l| l.priority()) {
-            Some(l) => (l.target(), l.priority()),
-            None => return Ok(false),
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    // changes the link path. the link is moved from the previous path when
+    // the links are next written
+    pub fn relocate(&mut self, path: &std::path::Path) -> bool {
+        if self.path == path {
+            return false;
+        }
+
+        let previous = std::mem::replace(&mut self.path, path.to_path_buf());
+
+        if self.previous_path.is_none() {
+            self.previous_path = Some(previous);
+        }
+
+        true
+    }
+
+    pub fn set_kind(&mut self, kind: GroupKind) -> bool {
+        if self.kind == kind {
+            return false;
+        }
+
+        self.kind = kind;
+
+        true
+    }
+
+    pub fn kind(&self) -> &GroupKind {
+        &self.kind
+    }
+
+    // the environment variable that the selection is exported as, if this is
+    // a variable group
+    pub fn variable(&self) -> Option<&str> {
+        match self.kind {
+            GroupKind::Variable{ ref name } => Some(name),
+            _ => None,
+        }
+    }
+
+    pub fn is_shim(&self) -> bool {
+        self.shim
+    }
+
+    pub fn set_shim(&mut self, shim: bool) -> bool {
+        if self.shim == shim {
+            return false;
+        }
+
+        self.shim = shim;
+
+        true
+    }
+
+    pub fn link_strategy(&self) -> LinkStrategy {
+        self.link_strategy.unwrap_or(self.settings.link_strategy())
+    }
+
+    // sets the link strategy of this group, or with None, falls back to the
+    // global one
+    pub fn set_link_strategy(&mut self, strategy: Option<LinkStrategy>)
+    -> bool {
+        if self.link_strategy == strategy {
+            return false;
+        }
+
+        self.link_strategy = strategy;
+
+        true
+    }
+
+    pub fn policy(&self) -> Policy {
+        self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: Policy) -> bool {
+        if self.policy == policy {
+            return false;
+        }
+
+        self.policy = policy;
+
+        true
+    }
+
+    // selects alternatives by policy instead of the configured one until
+    // the list is dropped. the custom policy is never written out
+    pub fn set_custom_policy(&mut self, policy: Box<dyn SelectionPolicy>) {
+        self.custom_policy = Some(policy);
+    }
+
+    pub fn selection_policy(&self) -> &dyn SelectionPolicy {
+        match self.custom_policy {
+            Some(ref p) => p.as_ref(),
+            None => &self.policy,
+        }
+    }
+
+    pub fn tie_policy(&self) -> TiePolicy {
+        self.tie_policy.unwrap_or(self.settings.tie_policy())
+    }
+
+    // sets how ties between the highest priority alternatives of this group
+    // are broken, or with None, falls back to the global policy
+    pub fn set_tie_policy(&mut self, policy: Option<TiePolicy>) -> bool {
+        if self.tie_policy == policy {
+            return false;
+        }
+
+        self.tie_policy = policy;
+
+        true
+    }
+
+    pub fn preferred_tags(&self) -> &[String] {
+        match self.preferred_tags {
+            Some(ref t) => t,
+            None => self.settings.preferred_tags(),
+        }
+    }
+
+    // sets the tags preferred for this group, or with None, falls back to
+    // the global preference
+    pub fn set_preferred_tags(&mut self, tags: Option<Vec<String>>) -> bool {
+        if self.preferred_tags == tags {
+            return false;
+        }
+
+        self.preferred_tags = tags;
+
+        true
+    }
+
+    // selects target regardless of the policy for as long as it is
+    // eligible, or with None, goes back to selecting by policy
+    pub fn set_manual(&mut self, target: Option<std::path::PathBuf>) -> bool {
+        if self.manual == target && self.expiry.is_none() {
+            return false;
+        }
+
+        self.manual = target;
+        self.expiry = None;
+
+        true
+    }
+
+    // selects target by hand for seconds, after which the group reverts to
+    // what it selected before. extending a selection that has not expired
+    // yet still reverts to what came before it
+    pub fn set_manual_for(&mut self, target: std::path::PathBuf,
+                          seconds: u64) {
+        let previous = match self.expiry.take() {
+            Some(e) => e.previous,
+            None => self.manual.take(),
+        };
+
+        self.manual = Some(target);
+        self.expiry = Some(Expiry{ until: self.now().saturating_add(seconds),
+                                   previous });
+    }
+
+    // the time at which the selection made by hand reverts, if it does
+    pub fn expires_at(&self) -> Option<u64> {
+        self.expiry.as_ref().map(|e| e.until)
+    }
+
+    // reverts the selection made by hand if it has expired, returning the
+    // target selected by hand before it, if there was one
+    pub fn expire(&mut self) -> Option<Option<std::path::PathBuf>> {
+        let now = self.now();
+
+        match self.expiry {
+            Some(ref e) if e.until <= now => (),
+            _ => return None,
+        }
+
+        let expiry = self.expiry.take().unwrap();
+        self.manual = expiry.previous.clone();
+
+        Some(expiry.previous)
+    }
+
+    // the target selected by hand, taking into account expiry that has not
+    // been applied yet
+    fn manual(&self) -> Option<&std::path::Path> {
+        match self.expiry {
+            Some(ref e) if e.until <= self.now() => e.previous.as_deref(),
+            _ => self.manual.as_deref(),
+        }
+    }
+
+    // holds the group at its current selection, or with None, releases it
+    pub fn set_hold(&mut self, hold: Option<Hold>) -> bool {
+        if self.hold == hold {
+            return false;
+        }
+
+        self.hold = hold;
+
+        true
+    }
+
+    pub fn hold(&self) -> Option<&Hold> {
+        self.hold.as_ref()
+    }
+
+    // treats the group as if it were not held, without releasing the hold
+    pub fn ignore_hold(&mut self) {
+        self.ignore_hold = true;
+    }
+
+    // the hold that is in effect, if any
+    pub fn active_hold(&self) -> Option<&Hold> {
+        if self.ignore_hold {
+            return None;
+        }
+
+        self.hold.as_ref()
+    }
+
+    pub fn constraints(&self) -> &[Constraint] {
+        &self.constraints
+    }
+
+    pub fn add_constraint(&mut self, constraint: Constraint) -> bool {
+        if self.constraints.contains(&constraint) {
+            return false;
+        }
+
+        self.constraints.push(constraint);
+
+        true
+    }
+
+    pub fn remove_constraint(&mut self, constraint: &Constraint) -> bool {
+        let before = self.constraints.len();
+        self.constraints.retain(|c| c != constraint);
+
+        self.constraints.len() != before
+    }
+
+    // whether the recorded hashes or the pending move of the link changed
+    // since the last call, in which case the list should be written out again
+    pub fn take_record_changed(&mut self) -> bool {
+        std::mem::replace(&mut self.record_changed, false)
+    }
+
+    pub fn alternatives(&self) -> &[Alternative] {
+        &self.links
+    }
+
+    pub fn alternative<P: std::convert::AsRef<std::path::Path>>(
+        &self, target: P
+    ) -> Option<&Alternative> {
+        let target_path = target.as_ref();
+
+        self.links.iter().find(|a| a.target() == target_path)
+    }
+
+    // finds the alternative whose target is name, or failing that, the
+    // alternative whose target has the file name name
+    pub fn find(&self, name: &str) -> Option<&Alternative> {
+        self.alternative(name).or_else(|| {
+            self.links.iter().find(|a| {
+                a.target().file_name() == Some(std::ffi::OsStr::new(name))
+            })
+        })
+    }
+
+    pub fn ineligible_reason(&self, alternative: &Alternative)
+    -> Option<String> {
+        let target = match self.chained.get(alternative.target()) {
+            Some(Some(resolved)) => self.on_disk(resolved),
+            Some(None) => {
+                return Some(String::from("target is the link of a group \
+                                          that selects nothing usable"));
+            },
+            None => self.on_disk(alternative.target()),
         };
 
-        if self.path.exists() {
-            if self.path.is_file() {
-                if let Ok(p) = self.path.read_link() {
-                    if p == target {
-                        return Ok(false);
-                    }
+        if !target.exists() {
+            return Some(String::from("target does not exist"));
+        }
+
+        match self.kind {
+            GroupKind::Link if self.runs(alternative)
+                && !filesystem::is_executable(&target) => {
+                return Some(String::from("target is not executable"));
+            },
+            GroupKind::Directory if !target.is_dir() => {
+                return Some(String::from("target is not a directory"));
+            },
+            _ => (),
+        }
+
+        alternative.conditions()
+            .iter()
+            .filter_map(|c| c.unmet(&target, self.root.as_deref()))
+            .next()
+    }
+
+    // whether alternative is run through the link, which is the case when
+    // the link is in a bin directory, is dispatched by a shim or is a
+    // wrapper script. other links, such as manual pages, need not be
+    // executable
+    fn runs(&self, alternative: &Alternative) -> bool {
+        let in_bin = self.path()
+            .parent()
+            .and_then(std::path::Path::file_name)
+            .is_some_and(|d| d == "bin" || d == "sbin");
+
+        in_bin || self.shim || !alternative.kind().is_symlink()
+    }
+
+    pub fn select(&self) -> Option<&Alternative> {
+        if let Some(hold) = self.active_hold() {
+            return self.alternative(hold.target()?);
+        }
+
+        self.select_unheld()
+    }
+
+    // the alternative that would be selected if the group were not held
+    fn select_unheld(&self) -> Option<&Alternative> {
+        if let Some(manual) = self.manual_selection() {
+            return Some(manual);
+        }
+
+        self.choose(self.best(|_| true))
+    }
+
+    // the alternative that would be selected if only targets under prefix
+    // were eligible
+    pub fn select_under(&self, prefix: &std::path::Path)
+    -> Option<&Alternative> {
+        self.choose(self.best(|a| a.target().starts_with(prefix)))
+    }
+
+    // the target selected by hand, if it is still eligible
+    fn manual_selection(&self) -> Option<&Alternative> {
+        let manual = self.alternative(self.manual()?)?;
+
+        if self.ineligible_reason(manual).is_some() {
+            return None;
+        }
+
+        Some(manual)
+    }
+
+    // breaks ties between the best alternatives according to the tie policy
+    fn choose<'a>(&self, best: Vec<&'a Alternative>)
+    -> Option<&'a Alternative> {
+        match self.tie_policy() {
+            // alternatives added before timestamps were recorded sort first,
+            // and among them the last one wins, as it always did
+            TiePolicy::Newest => best.into_iter()
+                .enumerate()
+                .max_by_key(|&(i, a)| (a.added(), i))
+                .map(|(_, a)| a),
+            TiePolicy::Target => best.into_iter()
+                .min_by(|a, b| a.target().cmp(b.target())),
+            TiePolicy::Error if best.len() == 1 => Some(best[0]),
+            TiePolicy::Error => None,
+        }
+    }
+
+    // the alternatives that are tied for selection and that the tie policy
+    // refuses to choose between
+    pub fn tie(&self) -> Vec<&Alternative> {
+        let best = self.best(|_| true);
+
+        if self.tie_policy() != TiePolicy::Error || best.len() < 2
+            || self.active_hold().is_some()
+            || self.manual_selection().is_some() {
+            return Vec::new();
+        }
+
+        best
+    }
+
+    // describes the tie that prevents selection, if there is one
+    pub fn tie_problem(&self) -> Option<String> {
+        let tie = self.tie();
+
+        if tie.is_empty() {
+            return None;
+        }
+
+        let targets: Vec<String> = tie.iter()
+            .map(|a| a.target().display().to_string())
+            .collect();
+
+        Some(format!("{} cannot be selected, as {} are ranked equally",
+                     self.path.display(), targets.join(", ")))
+    }
+
+    // the eligible alternatives kept by filter with the most preferred tag
+    // that the selection policy ranks highest
+    fn best<F: Fn(&Alternative) -> bool>(&self, filter: F)
+    -> Vec<&Alternative> {
+        let eligible: Vec<&Alternative> = self.links
+            .iter()
+            .filter(|a| filter(a) && self.ineligible_reason(a).is_none())
+            .collect();
+        let preferred = selection::highest_by(&eligible, |a| {
+            std::cmp::Reverse(self.tag_rank(a))
+        });
+
+        self.selection_policy().best(&preferred)
+    }
+
+    // the position of the first preferred tag of alternative, or the number
+    // of preferred tags if it has none of them
+    fn tag_rank(&self, alternative: &Alternative) -> usize {
+        let preferred = self.preferred_tags();
+
+        preferred.iter()
+            .position(|t| alternative.tags().contains(t))
+            .unwrap_or(preferred.len())
+    }
+
+    pub fn make_symlink(&mut self) -> std::io::Result<bool> {
+        if let Some(hold) = self.active_hold() {
+            let held = hold.target();
+            let unheld = self.select_unheld().map(Alternative::target);
+
+            if held != unheld {
+                eprintln!("update-alternatives: leaving {} unchanged, as it is \
+                          {}", self.path.display(), hold);
+            }
+
+            return Ok(false);
+        }
+
+        let previous = self.previous_path.clone();
+        let mut changed = match previous {
+            Some(ref p) => self.move_link(p),
+            None => false,
+        };
+
+        if self.install()? {
+            changed = true;
+        }
+
+        if let Some(ref p) = previous {
+            if self.remove_previous(p)? {
+                changed = true;
+            }
+
+            self.previous_path = None;
+            self.record_changed = true;
+        }
+
+        Ok(changed)
+    }
+
+    // renames the link at previous to the current path, so that there is no
+    // moment without either. returns false if the link could not be renamed,
+    // for example because the paths are on different filesystems
+    fn move_link(&mut self, previous: &std::path::Path) -> bool {
+        let from = self.on_disk(previous);
+        let to = self.on_disk(&self.path);
+
+        if !self.is_managed(previous)
+            || (to.symlink_metadata().is_ok() && !self.is_managed(&self.path)) {
+            return false;
+        }
+
+        if std::fs::rename(&from, &to).is_err() {
+            return false;
+        }
+
+        if let Some(hash) = self.hashes.remove(previous) {
+            self.hashes.insert(self.path.clone(), hash);
+            self.record_changed = true;
+        }
+
+        eprintln!("update-alternatives: moved {} to {}", previous.display(),
+                  self.path.display());
+
+        true
+    }
+
+    fn remove_previous(&mut self, previous: &std::path::Path)
+    -> std::io::Result<bool> {
+        if !self.is_managed(previous) {
+            return Ok(false);
+        }
+
+        filesystem::remove(self.on_disk(previous))?;
+        self.forget_hash(previous);
+
+        eprintln!("update-alternatives: removed {}, as the link was moved to \
+                  {}", previous.display(), self.path.display());
+
+        Ok(true)
+    }
+
+    fn install(&mut self) -> std::io::Result<bool> {
+        for alternative in self.links.iter() {
+            if let Some(reason) = self.ineligible_reason(alternative) {
+                eprintln!("update-alternatives: skipping alternative {} for \
+                          {}: {}", alternative.target().display(),
+                          self.path.display(), reason);
+            }
+        }
+
+        if let Some(variable) = self.variable().map(str::to_string) {
+            return self.remove_link(&format!("its selection is exported as {}",
+                                             variable));
+        }
+
+        if let Some(problem) = self.tie_problem() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput,
+                                           problem));
+        }
+
+        let selected = match self.select() {
+            Some(l) => l.clone(),
+            None => return self.remove_link("none of its alternatives are \
+                                             usable"),
+        };
+
+        if let Some(reason) = self.unsupported(&selected) {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput,
+                                           format!("{}: {}",
+                                                   self.path.display(),
+                                                   reason)));
+        }
+
+        let path = self.path.clone();
+        let mut changed = false;
+
+        if self.shim {
+            let executable = std::env::current_exe()?;
+
+            if self.place(&path, &executable)? {
+                eprintln!("update-alternatives: created shim from {} to {}",
+                          path.display(), executable.display());
+                changed = true;
+            }
+        } else if let Some(script) = selected.wrapper_script() {
+            if wrap(&self.on_disk(&path), &script)? {
+                eprintln!("update-alternatives: created wrapper at {} for {} \
+                          with priority {}", path.display(),
+                          selected.target().display(), selected.priority());
+                changed = true;
+            }
+        } else if self.place(&path, selected.target())? {
+            eprintln!("update-alternatives: created {} from {} to {} with \
+                      priority {}", self.link_strategy(), path.display(),
+                      selected.target().display(), selected.priority());
+            changed = true;
+        }
+
+        for (path, target) in selected.followers().iter() {
+            if self.place(path, target)? {
+                eprintln!("update-alternatives: created follower {} from {} to \
+                          {}", self.link_strategy(), path.display(),
+                          target.display());
+                changed = true;
+            }
+        }
+
+        if self.remove_followers(selected.followers())? {
+            changed = true;
+        }
+
+        Ok(changed)
+    }
+
+    // describes each way in which the links on disk differ from what
+    // make_symlink would create
+    pub fn check(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if let Some(problem) = self.tie_problem() {
+            problems.push(problem);
+
+            return problems;
+        }
+
+        let selected = match (self.variable(), self.select()) {
+            (None, Some(s)) => s,
+            (variable, _) => {
+                let reason = match variable {
+                    Some(v) => format!("its selection is exported as {}", v),
+                    None => String::from("none of its alternatives are usable"),
+                };
+
+                if self.is_managed(&self.path) {
+                    problems.push(format!("{} should have been removed, as {}",
+                                          self.path.display(), reason));
                 }
+
+                return problems;
             }
+        };
+
+        let problem = if let Some(reason) = self.unsupported(selected) {
+            Some(reason.to_string())
+        } else if self.shim {
+            match std::env::current_exe() {
+                Ok(e) => self.placement_problem(&self.path, &e),
+                Err(e) => Some(format!("could not locate the shim: {}", e)),
+            }
+        } else if selected.kind().is_symlink() {
+            self.placement_problem(&self.path, selected.target())
+        } else {
+            self.wrapper_problem(&self.path, selected)
+        };
+
+        problems.extend(problem);
+
+        for (path, target) in selected.followers().iter() {
+            problems.extend(self.placement_problem(path, target));
+        }
+
+        problems
+    }
 
-            if let Err(e) = filesystem::remove(&self.path) {
-                return Err(e);
+    // describes each alternative whose target cannot run on this host
+    pub fn run_problems(&self) -> Vec<String> {
+        self.links
+            .iter()
+            .filter_map(|a| {
+                validate::run_problem(a.target(), &self.on_disk(a.target()),
+                                      self.root.as_deref())
+            })
+            .collect()
+    }
+
+    // why selected cannot be installed for this group, if it cannot
+    fn unsupported(&self, selected: &Alternative) -> Option<&'static str> {
+        if self.kind != GroupKind::Directory {
+            return None;
+        }
+
+        if self.shim {
+            Some("directories cannot be dispatched by a shim")
+        } else if !selected.kind().is_symlink() {
+            Some("directories cannot be wrapped")
+        } else if !self.link_strategy().is_symlink()
+            && self.link_strategy() != LinkStrategy::RelativeSymlink {
+            Some("directories can only be symlinked")
+        } else {
+            None
+        }
+    }
+
+    // links, hardlinks or copies target to path according to the link
+    // strategy, returning false if path was already up to date
+    fn place(&mut self, path: &std::path::Path,
+             target: &std::path::Path) -> std::io::Result<bool> {
+        let symlink_target = match self.link_strategy() {
+            LinkStrategy::Symlink => target.to_path_buf(),
+            LinkStrategy::RelativeSymlink => relative_target(path, target),
+            LinkStrategy::Hardlink | LinkStrategy::Copy => {
+                return self.place_file(path, target);
+            },
+        };
+
+        if self.hashes.contains_key(path) {
+            if !self.is_managed(path) {
+                return Err(not_managed(path));
             }
+
+            self.forget_hash(path);
+        }
+
+        link(&self.on_disk(path), &symlink_target)
+    }
+
+    fn place_file(&mut self, path: &std::path::Path,
+                  target: &std::path::Path) -> std::io::Result<bool> {
+        let source = self.on_disk(target);
+        let destination = self.on_disk(path);
+        let hash = filesystem::hash_file(&source)?;
+
+        if self.is_current(path, target, &hash) {
+            self.record_hash(path, hash);
+
+            return Ok(false);
         }
 
-        if let Err(e) = filesystem::symlink(target, &self.path) {
-            return Err(e);
+        if destination.symlink_metadata().is_ok() && !self.is_managed(path) {
+            return Err(not_managed(path));
         }
 
-        println!("update-alternatives: created symlink from {} to {} with \
-                 priority {}", self.path.display(), target.display(), priority);
+        if self.link_strategy() == LinkStrategy::Hardlink {
+            filesystem::hard_link(&source, &destination)?;
+        } else {
+            filesystem::copy(&source, &destination)?;
+        }
+
+        self.record_hash(path, hash);
+
         Ok(true)
     }
 
-    pub fn add_alternative(&mut self, to_add: Alternative) -> bool {
+    // whether path is already a hardlink to or a copy of target, whose
+    // contents hash to hash
+    fn is_current(&self, path: &std::path::Path, target: &std::path::Path,
+                  hash: &str) -> bool {
+        let destination = self.on_disk(path);
+
+        if filesystem::is_symlink(&destination) || !destination.is_file() {
+            return false;
+        }
+
+        match self.link_strategy() {
+            LinkStrategy::Hardlink => {
+                filesystem::same_file(&destination, self.on_disk(target))
+            },
+            _ => {
+                filesystem::hash_file(&destination).ok().as_deref() == Some(hash)
+            },
+        }
+    }
+
+    fn record_hash(&mut self, path: &std::path::Path, hash: String) {
+        if self.hashes.get(path) != Some(&hash) {
+            self.hashes.insert(path.to_path_buf(), hash);
+            self.record_changed = true;
+        }
+    }
+
+    fn forget_hash(&mut self, path: &std::path::Path) {
+        if self.hashes.remove(path).is_some() {
+            self.record_changed = true;
+        }
+    }
+
+    // whether path is a symlink, wrapper, hardlink or copy that
+    // update-alternatives placed and that has not been modified since
+    fn is_managed(&self, path: &std::path::Path) -> bool {
+        let destination = self.on_disk(path);
+
+        if filesystem::is_symlink(&destination)
+            || filesystem::is_wrapper(&destination) {
+            return true;
+        }
+
+        let hash = match self.hashes.get(path) {
+            Some(h) => h,
+            None => return false,
+        };
+
+        // a hardlink shares its contents with the target, which may have been
+        // modified in place since
+        filesystem::hash_file(&destination).ok().as_ref() == Some(hash)
+            || self.links.iter().any(|a| {
+                filesystem::same_file(&destination, self.on_disk(a.target()))
+            })
+    }
+
+    // whether path was placed by update-alternatives for target
+    fn is_placed_for(&self, path: &std::path::Path,
+                     target: &std::path::Path) -> bool {
+        if let Ok(p) = self.on_disk(path).read_link() {
+            return p == target || p == relative_target(path, target);
+        }
+
+        self.hashes.contains_key(path) && self.is_managed(path)
+    }
+
+    fn placement_problem(&self, path: &std::path::Path,
+                         target: &std::path::Path) -> Option<String> {
+        let kind = match self.link_strategy() {
+            LinkStrategy::Symlink => return self.symlink_problem(path, target),
+            LinkStrategy::RelativeSymlink => {
+                return self.symlink_problem(path,
+                                            &relative_target(path, target));
+            },
+            LinkStrategy::Hardlink => "a hardlink",
+            LinkStrategy::Copy => "a copy",
+        };
+
+        let hash = match filesystem::hash_file(self.on_disk(target)) {
+            Ok(h) => h,
+            Err(e) => return Some(format!("could not read {}: {}",
+                                          target.display(), e)),
+        };
+
+        if self.is_current(path, target, &hash) {
+            None
+        } else if self.hashes.contains_key(path) && self.is_managed(path) {
+            Some(format!("{} is an outdated {} of {}", path.display(),
+                         self.link_strategy(), target.display()))
+        } else {
+            Some(self.unexpected(path, kind))
+        }
+    }
+
+    fn wrapper_problem(&self, path: &std::path::Path,
+                       alternative: &Alternative) -> Option<String> {
+        let script = alternative.wrapper_script()?;
+        let destination = self.on_disk(path);
+
+        if !filesystem::is_wrapper(&destination) {
+            return Some(self.unexpected(path, "a wrapper"));
+        }
+
+        if filesystem::read(&destination).ok().as_deref() != Some(script.as_str()) {
+            return Some(format!("{} is an outdated wrapper for {}",
+                                path.display(),
+                                alternative.target().display()));
+        }
+
+        None
+    }
+
+    fn symlink_problem(&self, path: &std::path::Path,
+                       target: &std::path::Path) -> Option<String> {
+        match self.on_disk(path).read_link() {
+            Ok(ref p) if p == target => None,
+            Ok(p) => Some(format!("{} points to {} instead of {}",
+                                  path.display(), p.display(),
+                                  target.display())),
+            Err(_) => Some(self.unexpected(path, "a symlink")),
+        }
+    }
+
+    fn unexpected(&self, path: &std::path::Path, expected: &str) -> String {
+        if self.on_disk(path).symlink_metadata().is_err() {
+            format!("{} is missing", path.display())
+        } else if self.is_managed(path) {
+            format!("{} should be {}", path.display(), expected)
+        } else {
+            format!("{} is not managed by update-alternatives", path.display())
+        }
+    }
+
+    fn remove_link(&mut self, reason: &str) -> std::io::Result<bool> {
+        let mut changed = self.remove_followers(&Followers::new())?;
+        let path = self.path.clone();
+
+        if self.is_managed(&path) {
+            filesystem::remove(self.on_disk(&path))?;
+            self.forget_hash(&path);
+
+            eprintln!("update-alternatives: removed {}, as {}",
+                      self.path.display(), reason);
+            changed = true;
+        }
+
+        Ok(changed)
+    }
+
+    fn remove_followers(&mut self, to_keep: &Followers)
+    -> std::io::Result<bool> {
+        let mut changed = false;
+        let candidates: Followers = self.links
+            .iter()
+            .flat_map(|a| a.followers().clone())
+            .chain(self.removed_followers.clone())
+            .collect();
+
+        for (path, target) in candidates {
+            if to_keep.contains_key(&path) || !self.is_placed_for(&path, &target) {
+                continue;
+            }
+
+            std::fs::remove_file(self.on_disk(&path))?;
+            self.forget_hash(&path);
+
+            eprintln!("update-alternatives: removed follower {}",
+                      path.display());
+            changed = true;
+        }
+
+        Ok(changed)
+    }
+
+    pub fn add_alternative(&mut self, mut to_add: Alternative) -> bool {
         let target = to_add.target().to_path_buf();
 
         match self.links.iter().position(|a| a.target() == target) {
             Some(i) => {
-                if self.links[i].priority() == to_add.priority() {
+                to_add.inherit(&self.links[i]);
+
+                if self.links[i] == to_add {
                     return false;
                 }
-                
-                self.links[i] = to_add;
+
+                let replaced = std::mem::replace(&mut self.links[i], to_add);
+                self.removed_followers.extend(replaced.into_followers());
 
                 true
             }
             None => {
+                to_add.set_added(self.now());
                 self.links.push(to_add);
 
                 true
@@ -108,28 +1096,573 @@ impl AlternativeList {
         if let Some(p) = self.links
                              .iter()
                              .position(|a| a.target() == target_path) {
-            self.links.remove(p);
+            let removed = self.links.remove(p);
+
+            if self.manual.as_deref() == Some(target_path) {
+                self.manual = None;
+            }
+
+            self.removed_followers.extend(removed.into_followers());
 
             return true;
         }
 
         false
     }
+
+    // derives the priorities of alternatives from their versions again,
+    // returning the alternatives whose versions changed
+    pub fn rederive_priorities(&mut self) -> Vec<Alternative> {
+        let root = self.root.clone();
+        let mut changed = Vec::new();
+
+        for alternative in self.links.iter_mut() {
+            let source = match alternative.priority_from() {
+                Some(s) => s,
+                None => continue,
+            };
+
+            let on_disk = filesystem::under_root(root.as_deref(),
+                                                 alternative.target());
+
+            if let Some(version) = source.find(alternative.target(), &on_disk) {
+                if alternative.set_version(version) {
+                    changed.push(alternative.clone());
+                }
+            }
+        }
+
+        changed
+    }
+
+    // releases owner's reference to each matching alternative, returning the
+    // targets that were released and whether they were removed as a result
+    pub fn remove_owner(&mut self, owner: &str,
+                        target: Option<&std::path::Path>)
+    -> Vec<(std::path::PathBuf, bool)> {
+        let mut released = Vec::new();
+
+        for alternative in self.links.iter_mut() {
+            if target.is_some_and(|t| alternative.target() != t) {
+                continue;
+            }
+
+            if alternative.remove_owner(owner) {
+                released.push((alternative.target().to_path_buf(),
+                               !alternative.is_referenced()));
+            }
+        }
+
+        for &(ref released_target, removed) in released.iter() {
+            if removed {
+                self.remove_alternative(released_target);
+            }
+        }
+
+        released
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+// places a symlink or wrapper for alternative at path, returning false if an
+// identical one was already there
+pub fn install(path: &std::path::Path,
+               alternative: &Alternative) -> std::io::Result<bool> {
+    match alternative.wrapper_script() {
+        Some(script) => wrap(path, &script),
+        None => link(path, alternative.target()),
+    }
+}
+
+fn wrap(path: &std::path::Path, script: &str) -> std::io::Result<bool> {
+    if filesystem::is_wrapper(path)
+        && filesystem::read(path).ok().as_deref() == Some(script) {
+        return Ok(false);
+    }
+
+    filesystem::remove(path)?;
+    filesystem::write_script(script, path)?;
+
+    Ok(true)
+}
+
+fn not_managed(path: &std::path::Path) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::AlreadyExists,
+                        format!("{} is not managed by update-alternatives",
+                                path.display()))
+}
+
+// the target of a relative symlink at path that points to target
+fn relative_target(path: &std::path::Path,
+                   target: &std::path::Path) -> std::path::PathBuf {
+    match path.parent() {
+        Some(p) => filesystem::relative_path(p, target),
+        None => target.to_path_buf(),
+    }
+}
+
+fn link(path: &std::path::Path,
+        target: &std::path::Path) -> std::io::Result<bool> {
+    if let Ok(p) = path.read_link() {
+        if p == target {
+            return Ok(false);
+        }
+    }
+
+    filesystem::remove(path)?;
+
+    filesystem::symlink(target, path)?;
+
+    Ok(true)
 }
 
 impl std::fmt::Display for AlternativeList {
     fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        if let Err(e) = writeln!(formatter, "alternatives for {}:",
-                                 self.path.display()) {
-            return Err(e);
+        let mut notes = Vec::new();
+
+        if let Some(variable) = self.variable() {
+            notes.push(format!("exported as {}", variable));
+        } else if self.kind == GroupKind::Directory {
+            notes.push(String::from("directories"));
+        }
+
+        if self.shim {
+            notes.push(String::from("dispatched by shim"));
+        }
+
+        if !self.link_strategy().is_symlink() {
+            notes.push(format!("installed as {}", self.link_strategy()));
+        }
+
+        if let Some(ref hold) = self.hold {
+            notes.push(hold.to_string());
+        }
+
+        if let Some(manual) = self.manual() {
+            match self.expiry {
+                Some(ref e) if e.until > self.now() => {
+                    let remaining = clock::format_duration(e.until
+                                                           - self.now());
+
+                    notes.push(format!("manually set to {} for another {}",
+                                       manual.display(), remaining));
+                },
+                _ => notes.push(format!("manually set to {}",
+                                        manual.display())),
+            }
+        }
+
+        if !self.preferred_tags().is_empty() {
+            notes.push(format!("preferring {}",
+                               self.preferred_tags().join(", ")));
+        }
+
+        if self.custom_policy.is_some() {
+            notes.push(String::from("selected by a custom policy"));
+        } else if !self.policy.is_priority() {
+            notes.push(format!("selected by {}", self.policy));
+        }
+
+        if !self.tie_policy().is_newest() {
+            notes.push(format!("ties broken by {}", self.tie_policy()));
+        }
+
+        if notes.is_empty() {
+            writeln!(formatter, "alternatives for {}:", self.path.display())?;
+        } else {
+            writeln!(formatter, "alternatives for {} ({}):",
+                     self.path.display(), notes.join(", "))?;
         }
 
         for alternative in self.links.iter() {
-            if let Err(e) = writeln!(formatter, "    {}", alternative) {
-                return Err(e);
+            match self.ineligible_reason(alternative) {
+                Some(reason) => writeln!(formatter, "    {} (ineligible: {})",
+                                         alternative, reason)?,
+                None => writeln!(formatter, "    {}", alternative)?,
             }
         }
 
+        for constraint in self.constraints.iter() {
+            writeln!(formatter, "  {}", constraint)?;
+        }
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::clock::FixedClock;
+
+    static GCC: &str = "/usr/bin/gcc";
+    static CLANG: &str = "/usr/bin/clang";
+
+    fn group() -> AlternativeList {
+        let mut list = AlternativeList::new("/usr/local/bin/cc");
+
+        at(&mut list, 1000);
+        list.add_alternative(Alternative::from_parts(GCC, 10));
+        list.add_alternative(Alternative::from_parts(CLANG, 5));
+
+        list
+    }
+
+    // moves the clock of list to now
+    fn at(list: &mut AlternativeList, now: u64) {
+        let clock: std::rc::Rc<dyn Clock> = std::rc::Rc::new(FixedClock(now));
+
+        list.inherit(&Settings::default(), None, &clock);
+    }
+
+    fn header(list: &AlternativeList) -> String {
+        list.to_string().lines().next().unwrap().to_string()
+    }
+
+    fn has(list: &AlternativeList, target: &str) -> bool {
+        list.alternative(std::path::Path::new(target)).is_some()
+    }
+
+    #[test]
+    fn removes_alternatives_with_their_last_owner() {
+        let mut list = group();
+        list.add_alternative(Alternative::from_parts("/opt/cc", 1)
+                                 .owned_by("a"));
+        list.add_alternative(Alternative::from_parts("/opt/cc", 1)
+                                 .owned_by("b"));
+
+        assert_eq!(list.remove_owner("a", None),
+                   vec![(std::path::PathBuf::from("/opt/cc"), false)]);
+        assert!(has(&list, "/opt/cc"));
+
+        assert_eq!(list.remove_owner("b", None),
+                   vec![(std::path::PathBuf::from("/opt/cc"), true)]);
+        assert!(!has(&list, "/opt/cc"));
+        assert!(list.remove_owner("b", None).is_empty());
+    }
+
+    #[test]
+    fn counts_adding_by_hand_as_a_reference() {
+        let mut list = group();
+        list.add_alternative(Alternative::from_parts(GCC, 10).owned_by("a"));
+
+        assert_eq!(list.remove_owner("a", None),
+                   vec![(std::path::PathBuf::from(GCC), false)]);
+        assert!(has(&list, GCC));
+
+        list.add_alternative(Alternative::from_parts("/opt/cc", 1)
+                                 .owned_by("a"));
+        list.add_alternative(Alternative::from_parts("/opt/cc", 1));
+
+        assert_eq!(list.remove_owner("a", Some(std::path::Path::new(
+            "/opt/cc"
+        ))), vec![(std::path::PathBuf::from("/opt/cc"), false)]);
+        assert!(has(&list, "/opt/cc"));
+    }
+
+    #[test]
+    fn only_requires_executable_targets_for_run_links() {
+        let scratch = filesystem::ScratchDir::new("executable");
+        let clock: std::rc::Rc<dyn Clock> = std::rc::Rc::new(FixedClock(0));
+        let page = scratch.path().join("gcc.1.gz");
+        filesystem::write("", &page).unwrap();
+
+        let mut pages = AlternativeList::new("/usr/share/man/man1/cc.1.gz");
+        pages.inherit(&Settings::default(), None, &clock);
+        pages.add_alternative(Alternative::from_parts(&page, 10));
+
+        assert!(pages.select().is_some());
+
+        let mut programs = AlternativeList::new("/usr/local/bin/cc");
+        programs.inherit(&Settings::default(), None, &clock);
+        programs.add_alternative(Alternative::from_parts(&page, 10));
+
+        assert!(programs.select().is_none());
+        assert_eq!(programs.ineligible_reason(&programs.links[0]),
+                   Some(String::from("target is not executable")));
+
+        pages.set_shim(true);
+        assert!(pages.select().is_none());
+    }
+
+    // a group for cc placed under a scratch root with link strategy, whose
+    // only alternative is /opt/gcc
+    fn placed(scratch: &filesystem::ScratchDir, strategy: LinkStrategy)
+    -> AlternativeList {
+        let clock: std::rc::Rc<dyn Clock> = std::rc::Rc::new(FixedClock(0));
+        let root = scratch.path();
+        std::fs::create_dir_all(root.join("usr/local/bin")).unwrap();
+        std::fs::create_dir_all(root.join("opt")).unwrap();
+        filesystem::write_script("#!/bin/sh\n", root.join("opt/gcc"))
+            .unwrap();
+
+        let mut list = AlternativeList::new("/usr/local/bin/cc");
+        list.inherit(&Settings::default(), Some(root), &clock);
+        list.set_link_strategy(Some(strategy));
+        list.add_alternative(Alternative::from_parts("/opt/gcc", 10));
+
+        list
+    }
+
+    #[test]
+    fn leaves_current_copies_alone() {
+        let scratch = filesystem::ScratchDir::new("copy-current");
+        let mut list = placed(&scratch, LinkStrategy::Copy);
+        let link = scratch.path().join("usr/local/bin/cc");
+
+        assert!(list.make_symlink().unwrap());
+        assert!(list.take_record_changed());
+        assert_eq!(filesystem::read(&link).unwrap(), "#!/bin/sh\n");
+        assert!(!filesystem::is_symlink(&link));
+
+        assert!(!list.make_symlink().unwrap());
+        assert!(!list.take_record_changed());
+        assert!(list.check().is_empty());
+    }
+
+    #[test]
+    fn refuses_to_replace_modified_copies() {
+        let scratch = filesystem::ScratchDir::new("copy-modified");
+        let mut list = placed(&scratch, LinkStrategy::Copy);
+        let link = scratch.path().join("usr/local/bin/cc");
+
+        list.make_symlink().unwrap();
+        filesystem::write("#!/bin/sh\necho edited\n", &link).unwrap();
+
+        assert!(list.make_symlink().is_err());
+        assert_eq!(filesystem::read(&link).unwrap(),
+                   "#!/bin/sh\necho edited\n");
+    }
+
+    #[test]
+    fn refuses_to_replace_foreign_files() {
+        let scratch = filesystem::ScratchDir::new("copy-foreign");
+
+        for &strategy in [LinkStrategy::Copy, LinkStrategy::Hardlink].iter() {
+            let mut list = placed(&scratch, strategy);
+            let link = scratch.path().join("usr/local/bin/cc");
+            filesystem::write("foreign\n", &link).unwrap();
+
+            assert!(list.make_symlink().is_err());
+            assert_eq!(filesystem::read(&link).unwrap(), "foreign\n");
+            assert!(list.hashes.is_empty());
+        }
+    }
+
+    #[test]
+    fn forgets_hashes_when_switching_to_symlinks() {
+        let scratch = filesystem::ScratchDir::new("copy-switch");
+        let mut list = placed(&scratch, LinkStrategy::Copy);
+        let link = scratch.path().join("usr/local/bin/cc");
+
+        list.make_symlink().unwrap();
+        assert!(list.hashes.contains_key(std::path::Path::new(
+            "/usr/local/bin/cc"
+        )));
+        list.take_record_changed();
+
+        list.set_link_strategy(Some(LinkStrategy::Symlink));
+
+        assert!(list.make_symlink().unwrap());
+        assert!(list.take_record_changed());
+        assert!(list.hashes.is_empty());
+        assert_eq!(link.read_link().unwrap(),
+                   std::path::PathBuf::from("/opt/gcc"));
+    }
+
+    // executables named after each of names in scratch, which are equally
+    // ranked alternatives for a link that is not run
+    fn tied(scratch: &filesystem::ScratchDir, names: &[&str])
+    -> Vec<std::path::PathBuf> {
+        names.iter()
+            .map(|n| {
+                let target = scratch.path().join(n);
+                filesystem::write_script("#!/bin/sh\n", &target).unwrap();
+
+                target
+            })
+            .collect()
+    }
+
+    fn selected(list: &AlternativeList) -> Option<&std::path::Path> {
+        list.select().map(Alternative::target)
+    }
+
+    #[test]
+    fn breaks_ties_by_policy() {
+        let scratch = filesystem::ScratchDir::new("ties");
+        let targets = tied(&scratch, &["a", "b"]);
+        let mut list = AlternativeList::new("/usr/share/cc");
+
+        at(&mut list, 1000);
+        list.add_alternative(Alternative::from_parts(&targets[0], 10));
+        at(&mut list, 2000);
+        list.add_alternative(Alternative::from_parts(&targets[1], 10));
+
+        assert_eq!(selected(&list), Some(targets[1].as_path()));
+        assert!(list.tie().is_empty());
+
+        list.set_tie_policy(Some(TiePolicy::Target));
+        assert_eq!(selected(&list), Some(targets[0].as_path()));
+        list.links.reverse();
+        assert_eq!(selected(&list), Some(targets[0].as_path()));
+
+        list.set_tie_policy(Some(TiePolicy::Error));
+        assert_eq!(selected(&list), None);
+        assert_eq!(list.tie().len(), 2);
+        assert!(list.tie_problem().unwrap().contains("ranked equally"));
+
+        list.set_manual(Some(targets[1].clone()));
+        assert_eq!(selected(&list), Some(targets[1].as_path()));
+        assert!(list.tie().is_empty());
+    }
+
+    #[test]
+    fn prefers_alternatives_with_timestamps_when_newest() {
+        let scratch = filesystem::ScratchDir::new("ties-untimed");
+        let targets = tied(&scratch, &["a", "b", "c"]);
+        let mut list = AlternativeList::new("/usr/share/cc");
+
+        // recorded before timestamps were, so the last one wins among them
+        list.links.push(Alternative::from_parts(&targets[0], 10));
+        list.links.push(Alternative::from_parts(&targets[1], 10));
+        assert_eq!(selected(&list), Some(targets[1].as_path()));
+
+        at(&mut list, 1000);
+        list.add_alternative(Alternative::from_parts(&targets[2], 10));
+        assert_eq!(selected(&list), Some(targets[2].as_path()));
+
+        list.links.swap(0, 2);
+        assert_eq!(selected(&list), Some(targets[2].as_path()));
+    }
+
+    fn link_of(scratch: &filesystem::ScratchDir) -> std::path::PathBuf {
+        scratch.path().join("usr/local/bin/cc").read_link().unwrap()
+    }
+
+    #[test]
+    fn leaves_held_links_unchanged() {
+        let scratch = filesystem::ScratchDir::new("hold");
+        let mut list = placed(&scratch, LinkStrategy::Symlink);
+        let clang = scratch.path().join("opt/clang");
+        filesystem::write_script("#!/bin/sh\n", &clang).unwrap();
+        list.make_symlink().unwrap();
+
+        list.set_hold(Some(Hold::new(Some(std::path::Path::new("/opt/gcc")),
+                                     Some("testing"), 0)));
+        list.add_alternative(Alternative::from_parts("/opt/clang", 20));
+
+        assert!(!list.make_symlink().unwrap());
+        assert_eq!(link_of(&scratch), std::path::Path::new("/opt/gcc"));
+
+        // refresh after the held target is uninstalled
+        std::fs::remove_file(scratch.path().join("opt/gcc")).unwrap();
+        assert!(!list.make_symlink().unwrap());
+        assert_eq!(link_of(&scratch), std::path::Path::new("/opt/gcc"));
+
+        list.remove_alternative("/opt/gcc");
+        assert!(!list.make_symlink().unwrap());
+        assert_eq!(link_of(&scratch), std::path::Path::new("/opt/gcc"));
+        assert!(list.hold().is_some());
+    }
+
+    #[test]
+    fn ignoring_holds_changes_held_links() {
+        let scratch = filesystem::ScratchDir::new("hold-ignore");
+        let mut list = placed(&scratch, LinkStrategy::Symlink);
+        let clang = scratch.path().join("opt/clang");
+        filesystem::write_script("#!/bin/sh\n", &clang).unwrap();
+        list.make_symlink().unwrap();
+
+        list.set_hold(Some(Hold::new(Some(std::path::Path::new("/opt/gcc")),
+                                     None, 0)));
+        list.add_alternative(Alternative::from_parts("/opt/clang", 20));
+        list.ignore_hold();
+
+        assert!(list.make_symlink().unwrap());
+        assert_eq!(link_of(&scratch), std::path::Path::new("/opt/clang"));
+        assert!(list.hold().is_some());
+    }
+
+    #[test]
+    fn manual_selection_expires_at_deadline() {
+        let mut list = group();
+
+        list.set_manual_for(std::path::PathBuf::from(CLANG), 60);
+        assert_eq!(list.expires_at(), Some(1060));
+
+        at(&mut list, 1059);
+        assert!(list.expire().is_none());
+        assert_eq!(list.manual(), Some(std::path::Path::new(CLANG)));
+
+        at(&mut list, 1060);
+        assert_eq!(list.expire(), Some(None));
+        assert_eq!(list.manual(), None);
+        assert_eq!(list.expires_at(), None);
+    }
+
+    #[test]
+    fn expiry_reverts_to_previous_manual_selection() {
+        let mut list = group();
+
+        list.set_manual(Some(std::path::PathBuf::from(GCC)));
+        list.set_manual_for(std::path::PathBuf::from(CLANG), 60);
+
+        at(&mut list, 2000);
+        assert_eq!(list.manual(), Some(std::path::Path::new(GCC)));
+        assert_eq!(list.expire(), Some(Some(std::path::PathBuf::from(GCC))));
+        assert_eq!(list.manual(), Some(std::path::Path::new(GCC)));
+    }
+
+    #[test]
+    fn extending_keeps_what_came_before() {
+        let mut list = group();
+
+        list.set_manual(Some(std::path::PathBuf::from(GCC)));
+        list.set_manual_for(std::path::PathBuf::from(CLANG), 60);
+
+        at(&mut list, 1030);
+        list.set_manual_for(std::path::PathBuf::from(CLANG), 120);
+        assert_eq!(list.expires_at(), Some(1150));
+
+        at(&mut list, 1100);
+        assert!(list.expire().is_none());
+
+        at(&mut list, 1150);
+        assert_eq!(list.expire(), Some(Some(std::path::PathBuf::from(GCC))));
+    }
+
+    #[test]
+    fn setting_manually_clears_expiry() {
+        let mut list = group();
+
+        list.set_manual_for(std::path::PathBuf::from(CLANG), 60);
+        assert!(list.set_manual(Some(std::path::PathBuf::from(CLANG))));
+        assert_eq!(list.expires_at(), None);
+
+        at(&mut list, 5000);
+        assert!(list.expire().is_none());
+        assert_eq!(list.manual(), Some(std::path::Path::new(CLANG)));
+    }
+
+    #[test]
+    fn list_shows_remaining_time() {
+        let mut list = group();
+
+        list.set_manual_for(std::path::PathBuf::from(CLANG), 5400);
+        assert_eq!(header(&list),
+                   "alternatives for /usr/local/bin/cc (manually set to \
+                   /usr/bin/clang for another 1h 30m):");
+
+        at(&mut list, 6340);
+        assert_eq!(header(&list),
+                   "alternatives for /usr/local/bin/cc (manually set to \
+                   /usr/bin/clang for another 1m):");
+
+        at(&mut list, 6400);
+        assert_eq!(header(&list), "alternatives for /usr/local/bin/cc:");
+    }
+}
@@ -0,0 +1,157 @@
+// Copyright (c) 2018, Gregory Meyer
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+// the source of the current time, so that time-limited selections can be
+// evaluated at any moment
+pub trait Clock {
+    // the current time, in seconds since the epoch
+    fn now(&self) -> u64;
+}
+
+// the time of the running system
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+// a time that never changes
+pub struct FixedClock(pub u64);
+
+impl Clock for FixedClock {
+    fn now(&self) -> u64 {
+        self.0
+    }
+}
+
+static UNITS: &[(char, u64)] = &[('d', 86400), ('h', 3600), ('m', 60),
+                                  ('s', 1)];
+
+// parses a duration such as 90s, 2h or 1h30m into seconds
+pub fn parse_duration(s: &str) -> Result<u64, String> {
+    let mut seconds: u64 = 0;
+    let mut number = String::new();
+
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+
+            continue;
+        }
+
+        let unit = match UNITS.iter().find(|&&(u, _)| u == c) {
+            Some(&(_, u)) => u,
+            None => return Err(format!("{} is not a unit of d, h, m or s", c)),
+        };
+
+        let value: u64 = number.parse()
+            .map_err(|_| format!("{} has no number before {}", s, c))?;
+
+        seconds = value.checked_mul(unit)
+            .and_then(|v| seconds.checked_add(v))
+            .ok_or_else(|| format!("{} is too long", s))?;
+        number.clear();
+    }
+
+    if !number.is_empty() {
+        return Err(format!("{} has no unit after {}", s, number));
+    }
+
+    if seconds == 0 {
+        return Err(format!("{} is not a positive duration", s));
+    }
+
+    Ok(seconds)
+}
+
+// formats seconds as the two largest units of the duration, such as 1h 30m
+pub fn format_duration(seconds: u64) -> String {
+    let parts: Vec<String> = UNITS.iter()
+        .scan(seconds, |left, &(name, unit)| {
+            let count = *left / unit;
+            *left %= unit;
+
+            Some((count, name))
+        })
+        .skip_while(|&(count, _)| count == 0)
+        .take(2)
+        .filter(|&(count, _)| count > 0)
+        .map(|(count, name)| format!("{}{}", count, name))
+        .collect();
+
+    if parts.is_empty() {
+        String::from("0s")
+    } else {
+        parts.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("90s"), Ok(90));
+        assert_eq!(parse_duration("2h"), Ok(7200));
+        assert_eq!(parse_duration("1h30m"), Ok(5400));
+        assert_eq!(parse_duration("1d2h3m4s"), Ok(93784));
+    }
+
+    #[test]
+    fn rejects_malformed_durations() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("5").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("5w").is_err());
+        assert!(parse_duration("0s").is_err());
+        assert!(parse_duration("99999999999999999999d").is_err());
+    }
+
+    #[test]
+    fn formats_two_largest_units() {
+        assert_eq!(format_duration(0), "0s");
+        assert_eq!(format_duration(59), "59s");
+        assert_eq!(format_duration(5400), "1h 30m");
+        assert_eq!(format_duration(3601), "1h");
+        assert_eq!(format_duration(90061), "1d 1h");
+    }
+
+    #[test]
+    fn fixed_clock_does_not_move() {
+        let clock = FixedClock(42);
+
+        assert_eq!(clock.now(), 42);
+        assert_eq!(clock.now(), 42);
+    }
+}
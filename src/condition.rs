@@ -0,0 +1,161 @@
+// Copyright (c) 2018, Gregory Meyer
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+use super::elf;
+use super::filesystem;
+
+static CPUINFO: &str = "/proc/cpuinfo";
+
+// a predicate that must hold for an alternative to be eligible
+#[derive(Serialize, Deserialize, PartialEq, Clone)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Condition {
+    // the target is an ELF binary built for the architecture of this machine
+    Arch,
+    // path exists
+    Path { path: std::path::PathBuf },
+    // the processor advertises flag in /proc/cpuinfo, such as avx512f
+    Cpu { flag: String },
+    // the environment variable name is set, and equal to value if given
+    Env {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        value: Option<String>,
+    },
+}
+
+impl Condition {
+    // why the condition does not hold for target, found at on_disk, if it
+    // does not. paths are resolved under root
+    pub fn unmet(&self, on_disk: &std::path::Path,
+                 root: Option<&std::path::Path>) -> Option<String> {
+        match *self {
+            Condition::Arch => arch_unmet(on_disk),
+            Condition::Path{ ref path } => {
+                if filesystem::under_root(root, path).exists() {
+                    None
+                } else {
+                    Some(format!("{} does not exist", path.display()))
+                }
+            },
+            Condition::Cpu{ ref flag } => match cpu_flags() {
+                Ok(ref f) if f.iter().any(|g| g == flag) => None,
+                Ok(_) => Some(format!("the processor does not support {}",
+                                      flag)),
+                Err(e) => Some(format!("could not read {}: {}", CPUINFO, e)),
+            },
+            Condition::Env{ ref name, ref value } => {
+                match (std::env::var(name).ok(), value.as_ref()) {
+                    (Some(ref v), Some(expected)) if v == expected => None,
+                    (Some(_), None) => None,
+                    (_, Some(expected)) => {
+                        Some(format!("{} is not set to {}", name, expected))
+                    },
+                    (None, None) => Some(format!("{} is not set", name)),
+                }
+            },
+        }
+    }
+}
+
+impl std::str::FromStr for Condition {
+    type Err = String;
+
+    // parses arch, path=PATH, cpu=FLAG, env=NAME or env=NAME=VALUE
+    fn from_str(s: &str) -> Result<Condition, String> {
+        let (kind, argument) = match s.find('=') {
+            Some(i) => (&s[..i], Some(&s[i + 1..])),
+            None => (s, None),
+        };
+
+        match (kind, argument) {
+            ("arch", None) => Ok(Condition::Arch),
+            ("path", Some(p)) if !p.is_empty() => {
+                Ok(Condition::Path{ path: std::path::PathBuf::from(p) })
+            },
+            ("cpu", Some(f)) if !f.is_empty() => {
+                Ok(Condition::Cpu{ flag: f.to_string() })
+            },
+            ("env", Some(e)) if !e.is_empty() => {
+                let (name, value) = match e.find('=') {
+                    Some(i) => (&e[..i], Some(e[i + 1..].to_string())),
+                    None => (e, None),
+                };
+
+                Ok(Condition::Env{ name: name.to_string(), value })
+            },
+            _ => Err(format!("{} is not one of arch, path=PATH, cpu=FLAG, \
+                             env=NAME or env=NAME=VALUE", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Condition::Arch => write!(formatter, "arch"),
+            Condition::Path{ ref path } => {
+                write!(formatter, "path={}", path.display())
+            },
+            Condition::Cpu{ ref flag } => write!(formatter, "cpu={}", flag),
+            Condition::Env{ ref name, value: Some(ref v) } => {
+                write!(formatter, "env={}={}", name, v)
+            },
+            Condition::Env{ ref name, value: None } => {
+                write!(formatter, "env={}", name)
+            },
+        }
+    }
+}
+
+fn arch_unmet(on_disk: &std::path::Path) -> Option<String> {
+    match elf::Header::read(on_disk) {
+        Ok(Some(ref h)) if h.is_compatible() => None,
+        Ok(Some(h)) => Some(format!("target is built for {}, not {}", h,
+                                    elf::native())),
+        Ok(None) => Some(String::from("target is not an ELF binary")),
+        Err(e) => Some(format!("could not read target: {}", e)),
+    }
+}
+
+// the feature flags of the first processor in /proc/cpuinfo, which are
+// listed as flags on x86 and as Features on arm
+fn cpu_flags() -> std::io::Result<Vec<String>> {
+    let contents = filesystem::read(CPUINFO)?;
+
+    let line = contents.lines().find(|l| {
+        let key = l.split(':').next().unwrap_or("").trim();
+
+        key == "flags" || key == "Features"
+    });
+
+    Ok(match line.and_then(|l| l.split(':').nth(1)) {
+        Some(flags) => flags.split_whitespace().map(String::from).collect(),
+        None => Vec::new(),
+    })
+}
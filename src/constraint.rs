@@ -0,0 +1,80 @@
+// Copyright (c) 2018, Gregory Meyer
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+use super::alternative::Alternative;
+
+// requires another group to select a target under one directory while the
+// group declaring the constraint selects a target under another, such as
+// java and javac both selecting from the same JDK
+#[derive(Serialize, Deserialize, PartialEq, Clone)]
+pub struct Constraint {
+    when: std::path::PathBuf,
+    requires: String,
+    under: std::path::PathBuf,
+}
+
+impl Constraint {
+    pub fn new<P: std::convert::AsRef<std::path::Path>,
+               Q: std::convert::AsRef<std::path::Path>>(
+        when: P, requires: &str, under: Q
+    ) -> Constraint {
+        Constraint{ when: when.as_ref().to_path_buf(),
+                    requires: requires.to_string(),
+                    under: under.as_ref().to_path_buf() }
+    }
+
+    // the group whose selection is constrained
+    pub fn requires(&self) -> &str {
+        &self.requires
+    }
+
+    // the directory that the constrained group must select a target under
+    pub fn under(&self) -> &std::path::Path {
+        &self.under
+    }
+
+    // whether the constraint applies while selected is selected by the group
+    // that declares it
+    pub fn applies(&self, selected: Option<&Alternative>) -> bool {
+        selected.is_some_and(|a| a.target().starts_with(&self.when))
+    }
+
+    // whether selected, the selection of the constrained group, satisfies
+    // the constraint
+    pub fn is_met_by(&self, selected: Option<&Alternative>) -> bool {
+        selected.is_some_and(|a| a.target().starts_with(&self.under))
+    }
+}
+
+impl std::fmt::Display for Constraint {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "while selecting a target under {}, {} must select \
+                          one under {}", self.when.display(), self.requires,
+               self.under.display())
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright (c) 2018, Gregory Meyer
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+use super::alternative_db::AlternativeDb;
+use super::filesystem;
+use super::version::Version;
+
+pub struct Candidate {
+    target: std::path::PathBuf,
+    version: Option<Version>,
+}
+
+impl Candidate {
+    pub fn target(&self) -> &std::path::Path {
+        &self.target
+    }
+
+    pub fn version(&self) -> Option<&Version> {
+        self.version.as_ref()
+    }
+}
+
+// finds the executables in directories whose file names match any of
+// patterns, ordered by version. links managed by update-alternatives are
+// left out, as are directories already scanned under another name
+pub fn discover(db: &AlternativeDb, patterns: &[&str],
+                directories: &[std::path::PathBuf]) -> Vec<Candidate> {
+    let mut scanned = std::collections::HashSet::new();
+    let mut candidates = Vec::new();
+
+    for directory in directories.iter() {
+        let on_disk = filesystem::under_root(db.root(), directory);
+
+        match on_disk.canonicalize() {
+            Ok(c) => {
+                if !scanned.insert(c) {
+                    continue;
+                }
+            },
+            Err(_) => continue,
+        }
+
+        let entries = match on_disk.read_dir() {
+            Ok(e) => e,
+            Err(e) => {
+                eprintln!("update-alternatives: could not read {}: {}",
+                          directory.display(), e);
+
+                continue;
+            },
+        };
+
+        for entry in entries.filter_map(Result::ok) {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let target = directory.join(&file_name);
+
+            if !patterns.iter().any(|p| matches(p, &file_name))
+                || !filesystem::is_executable(entry.path())
+                || db.lists().any(|(_, l)| l.path() == target) {
+                continue;
+            }
+
+            candidates.push(Candidate{
+                target, version: Version::from_file_name(&file_name)
+            });
+        }
+    }
+
+    candidates.sort_by(|lhs, rhs| {
+        (&lhs.version, &lhs.target).cmp(&(&rhs.version, &rhs.target))
+    });
+
+    candidates
+}
+
+// matches name against a shell-style pattern, where * matches any sequence
+// of characters and ? matches any one character
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_literals() {
+        assert!(matches("gcc", "gcc"));
+        assert!(!matches("gcc", "gcc-14"));
+        assert!(!matches("gcc-14", "gcc"));
+    }
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(matches("gcc-*", "gcc-14"));
+        assert!(matches("gcc-*", "gcc-"));
+        assert!(!matches("gcc-*", "clang-18"));
+        assert!(matches("python3.?", "python3.9"));
+        assert!(!matches("python3.?", "python3.12"));
+        assert!(matches("*", ""));
+        assert!(matches("*-*-gcc", "x86_64-linux-gnu-gcc"));
+        assert!(!matches("*-*-gcc", "x86_64-gcc-ar"));
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright (c) 2018, Gregory Meyer
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+extern crate serde_json;
+
+use super::alternative::{Alternative, Followers};
+use super::condition::Condition;
+use super::filesystem;
+
+type Parsed = (Vec<(std::path::PathBuf, DropIn)>, Vec<std::path::PathBuf>);
+
+#[derive(Deserialize)]
+pub struct DropIn {
+    name: String,
+    target: std::path::PathBuf,
+    priority: i32,
+    #[serde(default)]
+    followers: Followers,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    conditions: Vec<Condition>,
+}
+
+impl DropIn {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn into_alternative<P: std::convert::AsRef<std::path::Path>>(
+        self, source: P
+    ) -> Alternative {
+        Alternative::from_drop_in(self.target, self.priority, self.followers,
+                                  source)
+            .with_tags(self.tags)
+            .with_conditions(self.conditions)
+    }
+}
+
+// returns the parsed drop-ins sorted by path, along with the paths of any
+// drop-ins that exist but could not be parsed
+pub fn read_folder<P: std::convert::AsRef<std::path::Path>>(folder: P)
+-> std::io::Result<Parsed> {
+    let folder_path = match folder.as_ref().canonicalize() {
+        Ok(p) => p,
+        Err(e) => {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                return Ok((Vec::new(), Vec::new()));
+            }
+
+            return Err(e);
+        },
+    };
+    let children = folder_path.read_dir()?;
+
+    let mut drop_ins = Vec::new();
+    let mut unreadable = Vec::new();
+
+    for child in children {
+        let entry = match child {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("update-alternatives: unable to read entry of \
+                          directory {}: {}", folder_path.display(), e);
+
+                continue;
+            },
+        };
+
+        let path = entry.path();
+
+        if !path.is_file() {
+            continue;
+        }
+
+        let contents = match filesystem::read(&path) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("update-alternatives: could not read file {}: {}",
+                          path.display(), e);
+                unreadable.push(path);
+
+                continue;
+            }
+        };
+
+        match serde_json::from_str::<DropIn>(&contents) {
+            Ok(ref d) if !filesystem::is_file_name(&d.name) => {
+                eprintln!("update-alternatives: {} names the group {:?}, \
+                          which is not a file name", path.display(), d.name);
+                unreadable.push(path);
+            },
+            Ok(d) => drop_ins.push((path, d)),
+            Err(e) => {
+                eprintln!("update-alternatives: unable to \
+                          deserialize {}: {}", path.display(), e);
+                unreadable.push(path);
+            }
+        }
+    }
+
+    drop_ins.sort_by(|lhs, rhs| lhs.0.cmp(&rhs.0));
+
+    Ok((drop_ins, unreadable))
+}
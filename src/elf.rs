@@ -0,0 +1,199 @@
+// Copyright (c) 2018, Gregory Meyer
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+use std::io::Read;
+
+static MAGIC: &[u8] = b"\x7fELF";
+
+// the fields of an ELF header that are needed to tell whether a binary can
+// run on this machine
+#[derive(PartialEq, Clone, Copy)]
+pub struct Header {
+    bits: u8,
+    machine: u16,
+}
+
+impl Header {
+    // reads the header of path, returning None if it is not an ELF file
+    pub fn read<P: std::convert::AsRef<std::path::Path>>(path: P)
+    -> std::io::Result<Option<Header>> {
+        let mut bytes = [0u8; 20];
+        let mut file = std::fs::File::open(path)?;
+        let mut filled = 0;
+
+        while filled < bytes.len() {
+            match file.read(&mut bytes[filled..])? {
+                0 => return Ok(None),
+                n => filled += n,
+            }
+        }
+
+        if &bytes[..4] != MAGIC {
+            return Ok(None);
+        }
+
+        let bits = match bytes[4] {
+            1 => 32,
+            2 => 64,
+            _ => return Ok(None),
+        };
+
+        let machine = match bytes[5] {
+            1 => u16::from_le_bytes([bytes[18], bytes[19]]),
+            2 => u16::from_be_bytes([bytes[18], bytes[19]]),
+            _ => return Ok(None),
+        };
+
+        Ok(Some(Header{ bits, machine }))
+    }
+
+    pub fn machine(&self) -> u16 {
+        self.machine
+    }
+
+    // whether the binary is 32-bit or 64-bit
+    pub fn bits(&self) -> u8 {
+        self.bits
+    }
+
+    // whether the binary was built for the architecture and word size of
+    // this machine
+    pub fn is_native(&self) -> bool {
+        native_machine() == Some(self.machine) && self.bits == native_bits()
+    }
+
+    // whether this machine can run the binary, either natively or as one of
+    // the 32-bit ABIs its architecture also runs, such as x86 on x86_64
+    pub fn is_compatible(&self) -> bool {
+        self.runs_on(std::env::consts::ARCH, native_bits())
+    }
+
+    // whether a machine of arch and word size bits can run the binary. on
+    // architectures that are not known, there is no telling, so every binary
+    // is assumed to run
+    fn runs_on(&self, arch: &str, bits: u8) -> bool {
+        let native = match machine_of(arch) {
+            Some(m) => m,
+            None => return true,
+        };
+
+        (native == self.machine && bits == self.bits)
+            || COMPATIBLE.iter().any(|&(a, machine, b)| {
+                a == arch && machine == self.machine && b == self.bits
+            })
+    }
+}
+
+impl std::fmt::Display for Header {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "{}-bit {}", self.bits, machine_name(self.machine))
+    }
+}
+
+// this machine's word size and architecture, as a Header would display them
+pub fn native() -> String {
+    format!("{}-bit {}", native_bits(), std::env::consts::ARCH)
+}
+
+#[cfg(target_pointer_width = "64")]
+fn native_bits() -> u8 {
+    64
+}
+
+#[cfg(not(target_pointer_width = "64"))]
+fn native_bits() -> u8 {
+    32
+}
+
+// the e_machine value of this machine's architecture, if it is one that is
+// known
+pub fn native_machine() -> Option<u16> {
+    machine_of(std::env::consts::ARCH)
+}
+
+fn machine_of(arch: &str) -> Option<u16> {
+    MACHINES.iter()
+        .find(|&&(_, name)| name == arch)
+        .map(|&(machine, _)| machine)
+}
+
+// the architecture named by machine, as std::env::consts::ARCH would name it
+pub fn machine_name(machine: u16) -> String {
+    MACHINES.iter()
+        .find(|&&(m, _)| m == machine)
+        .map(|&(_, name)| name.to_string())
+        .unwrap_or_else(|| format!("machine {}", machine))
+}
+
+// the architectures, and the e_machine values and word sizes of the binaries
+// other than their own that they run. aarch64 is left out, as many of its
+// processors cannot run 32-bit arm binaries
+static COMPATIBLE: &[(&str, u16, u8)] = &[
+    ("x86_64", 3, 32),
+    ("x86_64", 62, 32),
+    ("powerpc64", 20, 32),
+    ("s390x", 22, 32),
+];
+
+static MACHINES: &[(u16, &str)] = &[
+    (3, "x86"),
+    (8, "mips"),
+    (20, "powerpc"),
+    (21, "powerpc64"),
+    (22, "s390x"),
+    (40, "arm"),
+    (62, "x86_64"),
+    (183, "aarch64"),
+    (243, "riscv64"),
+    (258, "loongarch64"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(machine: u16, bits: u8) -> Header {
+        Header{ bits, machine }
+    }
+
+    #[test]
+    fn runs_native_and_compatible_binaries() {
+        assert!(header(62, 64).runs_on("x86_64", 64));
+        assert!(header(3, 32).runs_on("x86_64", 64));
+        assert!(header(62, 32).runs_on("x86_64", 64));
+        assert!(!header(183, 64).runs_on("x86_64", 64));
+        assert!(!header(40, 32).runs_on("aarch64", 64));
+        assert!(header(20, 32).runs_on("powerpc64", 64));
+    }
+
+    #[test]
+    fn assumes_unknown_hosts_run_everything() {
+        assert!(header(62, 64).runs_on("sparc64", 64));
+        assert!(header(8, 64).runs_on("mips64", 64));
+    }
+}
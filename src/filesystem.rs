@@ -26,16 +26,64 @@
 
 extern crate std;
 
+#[cfg(unix)]
+extern crate libc;
+
 use std::io::{Read, Write};
 
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum LinkStrategy {
+    #[default]
+    Symlink,
+    RelativeSymlink,
+    Hardlink,
+    Copy,
+}
+
+impl LinkStrategy {
+    pub fn from_name(name: &str) -> Option<LinkStrategy> {
+        match name {
+            "symlink" => Some(LinkStrategy::Symlink),
+            "relative-symlink" => Some(LinkStrategy::RelativeSymlink),
+            "hardlink" => Some(LinkStrategy::Hardlink),
+            "copy" => Some(LinkStrategy::Copy),
+            _ => None,
+        }
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        *self == LinkStrategy::Symlink
+    }
+}
+
+impl std::fmt::Display for LinkStrategy {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let description = match *self {
+            LinkStrategy::Symlink => "symlink",
+            LinkStrategy::RelativeSymlink => "relative symlink",
+            LinkStrategy::Hardlink => "hardlink",
+            LinkStrategy::Copy => "copy",
+        };
+
+        write!(formatter, "{}", description)
+    }
+}
+
 pub fn remove<P: std::convert::AsRef<std::path::Path>>(path: P)
 -> std::io::Result<()> {
     let concrete: &std::path::Path = path.as_ref();
 
-    if !concrete.exists() {
+    if is_symlink(concrete) {
+        std::fs::remove_file(concrete)
+    } else if !concrete.exists() {
         Ok(())
     } else if concrete.is_dir() {
-        std::fs::remove_dir_all(concrete)
+        // never recurse, as the directory may hold anything
+        std::fs::remove_dir(concrete).map_err(|e| {
+            std::io::Error::new(e.kind(), format!("could not remove {}: {}",
+                                                  concrete.display(), e))
+        })
     } else if concrete.is_file() {
         std::fs::remove_file(concrete)
     } else {
@@ -46,6 +94,30 @@ pub fn remove<P: std::convert::AsRef<std::path::Path>>(path: P)
     }
 }
 
+pub fn is_symlink<P: std::convert::AsRef<std::path::Path>>(path: P) -> bool {
+    match path.as_ref().symlink_metadata() {
+        Ok(m) => m.file_type().is_symlink(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(unix)]
+pub fn is_executable<P: std::convert::AsRef<std::path::Path>>(path: P)
+-> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    match std::fs::metadata(path) {
+        Ok(m) => m.is_file() && m.permissions().mode() & 0o111 != 0,
+        Err(_) => false,
+    }
+}
+
+#[cfg(windows)]
+pub fn is_executable<P: std::convert::AsRef<std::path::Path>>(path: P)
+-> bool {
+    path.as_ref().is_file()
+}
+
 #[cfg(unix)]
 pub fn symlink<P: std::convert::AsRef<std::path::Path>,
                Q: std::convert::AsRef<std::path::Path>>(
@@ -54,20 +126,172 @@ pub fn symlink<P: std::convert::AsRef<std::path::Path>,
     std::os::unix::fs::symlink(source, destination)
 }
 
+// directory symlinks are distinct from file symlinks on windows, so source is
+// resolved relative to destination to tell which to create
 #[cfg(windows)]
 pub fn symlink<P: std::convert::AsRef<std::path::Path>,
                Q: std::convert::AsRef<std::path::Path>>(
     source: P, destination: Q
 ) -> std::io::Result<()> {
-    std::os::windows::fs::symlink_file(source, destination)
+    let source = source.as_ref();
+    let destination = destination.as_ref();
+    let resolved = match destination.parent() {
+        Some(p) => p.join(source),
+        None => source.to_path_buf(),
+    };
+
+    if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(source, destination)
+    } else {
+        std::os::windows::fs::symlink_file(source, destination)
+    }
+}
+
+// the shortest path from the directory from to to, without consulting the
+// filesystem
+pub fn relative_path(from: &std::path::Path,
+                     to: &std::path::Path) -> std::path::PathBuf {
+    if !from.is_absolute() || !to.is_absolute() {
+        return to.to_path_buf();
+    }
+
+    let from = normalize(from);
+    let to = normalize(to);
+    let from_components: Vec<_> = from.components().collect();
+    let to_components: Vec<_> = to.components().collect();
+
+    let common = from_components.iter()
+        .zip(to_components.iter())
+        .take_while(|&(lhs, rhs)| lhs == rhs)
+        .count();
+
+    let mut relative = std::path::PathBuf::new();
+
+    for _ in common..from_components.len() {
+        relative.push("..");
+    }
+
+    for component in to_components[common..].iter() {
+        relative.push(component.as_os_str());
+    }
+
+    relative
+}
+
+// removes . and .. components without consulting the filesystem
+pub fn normalize(path: &std::path::Path) -> std::path::PathBuf {
+    let mut normalized = std::path::PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => (),
+            std::path::Component::ParentDir => {
+                normalized.pop();
+            },
+            c => normalized.push(c.as_os_str()),
+        }
+    }
+
+    normalized
+}
+
+// path as seen from the host when the managed tree is mounted at root
+pub fn under_root<P: std::convert::AsRef<std::path::Path>>(
+    root: Option<&std::path::Path>, path: P
+) -> std::path::PathBuf {
+    let path = path.as_ref();
+
+    match root {
+        Some(r) => r.join(path.strip_prefix("/").unwrap_or(path)),
+        None => path.to_path_buf(),
+    }
+}
+
+// an FNV-1a hash of the contents of path, used to recognize files that
+// update-alternatives placed itself
+pub fn hash_file<P: std::convert::AsRef<std::path::Path>>(path: P)
+-> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut buffer = vec![0u8; 65536];
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+
+    loop {
+        let read = file.read(&mut buffer)?;
+
+        if read == 0 {
+            break;
+        }
+
+        for &byte in buffer[..read].iter() {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    Ok(format!("fnv1a64:{:016x}", hash))
+}
+
+#[cfg(unix)]
+pub fn same_file<P: std::convert::AsRef<std::path::Path>,
+                 Q: std::convert::AsRef<std::path::Path>>(lhs: P, rhs: Q)
+-> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (lhs.as_ref().symlink_metadata(), std::fs::metadata(rhs)) {
+        (Ok(l), Ok(r)) => l.dev() == r.dev() && l.ino() == r.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(windows)]
+pub fn same_file<P: std::convert::AsRef<std::path::Path>,
+                 Q: std::convert::AsRef<std::path::Path>>(_: P, _: Q)
+-> bool {
+    false
+}
+
+pub fn hard_link<P: std::convert::AsRef<std::path::Path>,
+                 Q: std::convert::AsRef<std::path::Path>>(
+    source: P, destination: Q
+) -> std::io::Result<()> {
+    replace_with(destination.as_ref(), |temporary| {
+        std::fs::hard_link(source.as_ref(), temporary)
+    })
+}
+
+pub fn copy<P: std::convert::AsRef<std::path::Path>,
+            Q: std::convert::AsRef<std::path::Path>>(
+    source: P, destination: Q
+) -> std::io::Result<()> {
+    replace_with(destination.as_ref(), |temporary| {
+        std::fs::copy(source.as_ref(), temporary).map(|_| ())
+    })
+}
+
+// creates a file next to path and renames it into place, so that path is
+// never seen half-written
+fn replace_with<F: FnOnce(&std::path::Path) -> std::io::Result<()>>(
+    path: &std::path::Path, create: F
+) -> std::io::Result<()> {
+    let mut temporary = path.as_os_str().to_os_string();
+    temporary.push(".new");
+    let temporary = std::path::PathBuf::from(temporary);
+
+    remove(&temporary)?;
+    create(&temporary)?;
+
+    if let Err(e) = std::fs::rename(&temporary, path) {
+        let _ = std::fs::remove_file(&temporary);
+
+        return Err(e);
+    }
+
+    Ok(())
 }
 
 pub fn read<P: std::convert::AsRef<std::path::Path>>(path: P)
 -> std::io::Result<String> {
-    let mut file = match std::fs::File::open(path) {
-        Ok(f) => f,
-        Err(e) => return Err(e),
-    };
+    let mut file = std::fs::File::open(path)?;
 
     let mut buffer = String::new();
 
@@ -77,23 +301,243 @@ pub fn read<P: std::convert::AsRef<std::path::Path>>(path: P)
     }
 }
 
+// quotes word for a POSIX shell, leaving it as it is if nothing in it needs
+// quoting
+pub fn shell_quote(word: &str) -> String {
+    let is_plain = !word.is_empty() && word.chars().all(|c| {
+        c.is_ascii_alphanumeric() || "/._-+=:,".contains(c)
+    });
+
+    if is_plain {
+        return word.to_string();
+    }
+
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+// whether name can be assigned to as a shell variable, which also keeps it
+// from injecting commands into generated scripts
+pub fn is_shell_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => (),
+        _ => return false,
+    }
+
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// whether name can be used as a single file name, so that joining it to a
+// directory stays inside that directory
+pub fn is_file_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains('/')
+        && !name.contains('\0')
+}
+
+// installs handler for each of signals
+#[cfg(unix)]
+pub fn handle_signals(signals: &[libc::c_int],
+                      handler: extern "C" fn(libc::c_int))
+-> std::io::Result<()> {
+    for &signal in signals.iter() {
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handler as libc::sighandler_t;
+            libc::sigemptyset(&mut action.sa_mask);
+
+            if libc::sigaction(signal, &action, std::ptr::null_mut()) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// a directory under the temporary directory for tests to work in, removed
+// when it is dropped
+#[cfg(test)]
+pub struct ScratchDir(std::path::PathBuf);
+
+#[cfg(test)]
+impl ScratchDir {
+    pub fn new(name: &str) -> ScratchDir {
+        let path = std::env::temp_dir()
+            .join(format!("update-alternatives-{}-{}", name,
+                          std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+
+        std::fs::create_dir_all(&path).unwrap();
+
+        ScratchDir(path)
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+pub fn invalid(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, message)
+}
+
 pub fn create_dir<P: std::convert::AsRef<std::path::Path>>(path: P)
 -> std::io::Result<()> {
     std::fs::create_dir_all(path)
 }
 
+#[cfg(unix)]
+pub fn create_private_dir<P: std::convert::AsRef<std::path::Path>>(path: P)
+-> std::io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+
+    std::fs::DirBuilder::new().mode(0o700).create(path)
+}
+
+#[cfg(windows)]
+pub fn create_private_dir<P: std::convert::AsRef<std::path::Path>>(path: P)
+-> std::io::Result<()> {
+    std::fs::DirBuilder::new().create(path)
+}
+
+// whether path is a wrapper script generated by update-alternatives
+pub fn is_wrapper<P: std::convert::AsRef<std::path::Path>>(path: P) -> bool {
+    use super::alternative::WRAPPER_MARKER;
+
+    let concrete = path.as_ref();
+
+    if is_symlink(concrete) || !concrete.is_file() {
+        return false;
+    }
+
+    let mut head = Vec::new();
+
+    match std::fs::File::open(concrete) {
+        Ok(f) => {
+            if f.take(256).read_to_end(&mut head).is_err() {
+                return false;
+            }
+        },
+        Err(_) => return false,
+    }
+
+    String::from_utf8_lossy(&head).lines().nth(1) == Some(WRAPPER_MARKER)
+}
+
+pub fn write_script<P: std::convert::AsRef<std::path::Path>>(
+    contents: &str, path: P
+) -> std::io::Result<()> {
+    replace_with(path.as_ref(), |temporary| {
+        write(contents, temporary)?;
+
+        set_executable(temporary)
+    })
+}
+
+#[cfg(unix)]
+fn set_executable<P: std::convert::AsRef<std::path::Path>>(path: P)
+-> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+}
+
+#[cfg(windows)]
+fn set_executable<P: std::convert::AsRef<std::path::Path>>(_: P)
+-> std::io::Result<()> {
+    Ok(())
+}
+
 pub fn write<S, P: std::convert::AsRef<std::path::Path>>(contents: S, path: P)
 -> std::io::Result<usize> where String: std::convert::From<S> {
     let to_write = String::from(contents);
     let len = to_write.len();
 
-    let mut file = match std::fs::File::create(path) {
-        Ok(f) => f,
-        Err(e) => return Err(e),
-    };
+    let mut file = std::fs::File::create(path)?;
 
     match file.write_all(to_write.as_bytes()) {
         Ok(_) => Ok(len),
         Err(e) => Err(e),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn relative(from: &str, to: &str) -> std::path::PathBuf {
+        relative_path(std::path::Path::new(from), std::path::Path::new(to))
+    }
+
+    #[test]
+    fn relative_path_climbs_to_common_ancestor() {
+        assert_eq!(relative("/usr/local/bin", "/usr/bin/gcc"),
+                   std::path::Path::new("../../bin/gcc"));
+        assert_eq!(relative("/usr/bin", "/usr/bin/gcc"),
+                   std::path::Path::new("gcc"));
+        assert_eq!(relative("/opt/x", "/usr/bin/gcc"),
+                   std::path::Path::new("../../usr/bin/gcc"));
+    }
+
+    #[test]
+    fn relative_path_normalizes_first() {
+        assert_eq!(relative("/usr/local/./bin", "/usr/lib/../bin/gcc"),
+                   std::path::Path::new("../../bin/gcc"));
+    }
+
+    #[test]
+    fn relative_path_keeps_relative_targets() {
+        assert_eq!(relative("/usr/local/bin", "gcc"),
+                   std::path::Path::new("gcc"));
+    }
+
+    #[test]
+    fn under_root_joins_absolute_paths() {
+        let root = std::path::Path::new("/mnt/image");
+
+        assert_eq!(under_root(Some(root), "/usr/bin/gcc"),
+                   std::path::Path::new("/mnt/image/usr/bin/gcc"));
+        assert_eq!(under_root(None, "/usr/bin/gcc"),
+                   std::path::Path::new("/usr/bin/gcc"));
+    }
+
+    #[test]
+    fn shell_quote_quotes_only_when_needed() {
+        assert_eq!(shell_quote("/usr/bin/gcc"), "/usr/bin/gcc");
+        assert_eq!(shell_quote("A=1"), "A=1");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("x y"), "'x y'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote("$(id)"), "'$(id)'");
+    }
+
+    #[test]
+    fn file_names() {
+        assert!(is_file_name("cc"));
+        assert!(is_file_name("ld.gold"));
+        assert!(is_file_name("..cc"));
+        assert!(!is_file_name(""));
+        assert!(!is_file_name("."));
+        assert!(!is_file_name(".."));
+        assert!(!is_file_name("../../../escaped"));
+        assert!(!is_file_name("bin/cc"));
+    }
+
+    #[test]
+    fn shell_identifiers() {
+        assert!(is_shell_identifier("JAVA_HOME"));
+        assert!(is_shell_identifier("_x1"));
+        assert!(!is_shell_identifier(""));
+        assert!(!is_shell_identifier("1X"));
+        assert!(!is_shell_identifier("E;id>/tmp/x;F"));
+        assert!(!is_shell_identifier("A-B"));
+    }
+}
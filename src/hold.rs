@@ -0,0 +1,83 @@
+// Copyright (c) 2018, Gregory Meyer
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+// keeps a group on the target it selected when the hold was placed, so that
+// it does not change until the hold is released
+#[derive(Serialize, Deserialize, PartialEq, Clone)]
+pub struct Hold {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target: Option<std::path::PathBuf>,
+    by: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    // when the hold was placed, in seconds since the epoch
+    since: u64,
+}
+
+impl Hold {
+    // a hold on target placed at since by the invoking user, taken from
+    // SUDO_USER or else USER
+    pub fn new(target: Option<&std::path::Path>, reason: Option<&str>,
+               since: u64) -> Hold {
+        let by = std::env::var("SUDO_USER")
+            .or_else(|_| std::env::var("USER"))
+            .unwrap_or_else(|_| String::from("unknown"));
+
+        Hold{ target: target.map(std::path::Path::to_path_buf), by,
+              reason: reason.map(str::to_string), since }
+    }
+
+    // the target that the group is held at, if it selected one
+    pub fn target(&self) -> Option<&std::path::Path> {
+        self.target.as_deref()
+    }
+
+    pub fn by(&self) -> &str {
+        &self.by
+    }
+
+    pub fn reason(&self) -> Option<&str> {
+        self.reason.as_deref()
+    }
+
+    pub fn since(&self) -> u64 {
+        self.since
+    }
+}
+
+impl std::fmt::Display for Hold {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "held by {}", self.by)?;
+
+        if let Some(ref reason) = self.reason {
+            write!(formatter, ": {}", reason)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,244 @@
+// Copyright (c) 2018, Gregory Meyer
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+use std::fmt::Write;
+
+use super::alternative::{Alternative, LinkKind};
+use super::alternative_list::{AlternativeList, GroupKind};
+use super::filesystem::shell_quote as quote;
+
+pub fn alpm(executable: &std::path::Path, drop_in_folder: &std::path::Path,
+            group: Option<(&str, &AlternativeList)>) -> String {
+    let mut hook = String::new();
+
+    writeln!(hook, "[Trigger]").unwrap();
+    writeln!(hook, "Operation = Install").unwrap();
+    writeln!(hook, "Operation = Upgrade").unwrap();
+    writeln!(hook, "Operation = Remove").unwrap();
+    writeln!(hook, "Type = Path").unwrap();
+
+    match group {
+        Some((_, list)) => {
+            for alternative in list.alternatives().iter() {
+                writeln!(hook, "Target = {}",
+                         relative_to_root(alternative.target())
+                             .display()).unwrap();
+            }
+        },
+        None => writeln!(hook, "Target = {}",
+                         relative_to_root(&drop_in_folder.join("*"))
+                             .display()).unwrap(),
+    }
+
+    writeln!(hook).unwrap();
+    writeln!(hook, "[Action]").unwrap();
+
+    match group {
+        Some((name, _)) => writeln!(hook, "Description = Updating \
+                                    alternatives for {}...", name).unwrap(),
+        None => writeln!(hook, "Description = Updating \
+                         alternatives...").unwrap(),
+    }
+
+    writeln!(hook, "When = PostTransaction").unwrap();
+
+    match group {
+        Some(_) => writeln!(hook, "Exec = {} refresh",
+                            executable.display()).unwrap(),
+        None => writeln!(hook, "Exec = {} sync --directory {}",
+                         executable.display(),
+                         drop_in_folder.display()).unwrap(),
+    }
+
+    hook
+}
+
+// the owner that rpm scriptlets register alternatives for, which is the
+// package they belong to
+static RPM_OWNER: &str = "%{name}";
+
+// the owner that Debian maintainer scripts register alternatives for
+static DEB_OWNER: &str = "\"$DPKG_MAINTSCRIPT_PACKAGE\"";
+
+pub fn rpm(name: &str, list: &AlternativeList,
+           target: Option<&std::path::Path>) -> Result<String, String> {
+    check_reproducible(list, target)?;
+
+    let mut scriptlets = String::new();
+
+    writeln!(scriptlets, "%post").unwrap();
+
+    for command in add_commands(name, list, target, RPM_OWNER) {
+        writeln!(scriptlets, "{}", command).unwrap();
+    }
+
+    writeln!(scriptlets).unwrap();
+    writeln!(scriptlets, "%postun").unwrap();
+    writeln!(scriptlets, "if [ \"$1\" -eq 0 ]; then").unwrap();
+
+    for alternative in selected(list, target) {
+        writeln!(scriptlets, "    {}",
+                 remove_command(name, alternative, RPM_OWNER)).unwrap();
+    }
+
+    writeln!(scriptlets, "fi").unwrap();
+
+    Ok(scriptlets)
+}
+
+pub fn deb(name: &str, list: &AlternativeList,
+           target: Option<&std::path::Path>) -> Result<String, String> {
+    check_reproducible(list, target)?;
+
+    let mut snippets = String::new();
+
+    writeln!(snippets, "# postinst").unwrap();
+    writeln!(snippets, "case \"$1\" in").unwrap();
+    writeln!(snippets, "    configure)").unwrap();
+
+    for command in add_commands(name, list, target, DEB_OWNER) {
+        writeln!(snippets, "        {}", command).unwrap();
+    }
+
+    writeln!(snippets, "        ;;").unwrap();
+    writeln!(snippets, "esac").unwrap();
+    writeln!(snippets).unwrap();
+    writeln!(snippets, "# prerm").unwrap();
+    writeln!(snippets, "case \"$1\" in").unwrap();
+    writeln!(snippets, "    remove|deconfigure)").unwrap();
+
+    for alternative in selected(list, target) {
+        writeln!(snippets, "        {}",
+                 remove_command(name, alternative, DEB_OWNER)).unwrap();
+    }
+
+    writeln!(snippets, "        ;;").unwrap();
+    writeln!(snippets, "esac").unwrap();
+
+    Ok(snippets)
+}
+
+fn selected<'a>(list: &'a AlternativeList,
+                target: Option<&'a std::path::Path>)
+-> impl Iterator<Item = &'a Alternative> {
+    list.alternatives()
+        .iter()
+        .filter(move |a| target.is_none_or(|t| a.target() == t))
+}
+
+// refuses to generate commands for alternatives that the command line cannot
+// register as they are
+fn check_reproducible(list: &AlternativeList,
+                      target: Option<&std::path::Path>) -> Result<(), String> {
+    if let Some(t) = target {
+        if list.alternative(t).is_none() {
+            return Err(format!("{} is not an alternative for {}", t.display(),
+                               list.path().display()));
+        }
+    }
+
+    match selected(list, target).find(|a| !a.followers().is_empty()) {
+        Some(a) => Err(format!("{} has follower links, which can only be \
+                               declared by a drop-in", a.target().display())),
+        None => Ok(()),
+    }
+}
+
+// the commands that register the alternatives of list, followed by the one
+// that sets the kind of the group if it is not a link to an executable
+fn add_commands(name: &str, list: &AlternativeList,
+                target: Option<&std::path::Path>, owner: &str) -> Vec<String> {
+    let mut commands: Vec<String> = selected(list, target)
+        .map(|a| add_command(name, list, a, owner))
+        .collect();
+
+    match *list.kind() {
+        GroupKind::Link => (),
+        GroupKind::Directory => {
+            commands.push(format!("update-alternatives configure --name {} \
+                                  --kind directory", quote(name)));
+        },
+        GroupKind::Variable{ name: ref variable } => {
+            commands.push(format!("update-alternatives configure --name {} \
+                                  --kind variable --variable {}", quote(name),
+                                  quote(variable)));
+        },
+    }
+
+    commands
+}
+
+fn add_command(name: &str, list: &AlternativeList, alternative: &Alternative,
+               owner: &str) -> String {
+    let mut command = format!("update-alternatives add --name {} --target {} \
+                              --link {}", quote(name),
+                              quote(&alternative.target().to_string_lossy()),
+                              quote(&list.path().to_string_lossy()));
+
+    match alternative.priority_from() {
+        Some(source) => write!(command, " --priority-from {}", source),
+        None => write!(command, " --weight {}", alternative.priority()),
+    }.unwrap();
+
+    if let LinkKind::Wrapper{ ref args, ref env } = *alternative.kind() {
+        command += " --wrapper";
+
+        for (variable, value) in env.iter() {
+            write!(command, " --env={}",
+                   quote(&format!("{}={}", variable, value))).unwrap();
+        }
+
+        for arg in args.iter() {
+            write!(command, " --arg={}", quote(arg)).unwrap();
+        }
+    }
+
+    for tag in alternative.tags().iter() {
+        write!(command, " --tag {}", quote(tag)).unwrap();
+    }
+
+    for condition in alternative.conditions().iter() {
+        write!(command, " --condition {}",
+               quote(&condition.to_string())).unwrap();
+    }
+
+    write!(command, " --owner {}", owner).unwrap();
+
+    command
+}
+
+fn remove_command(name: &str, alternative: &Alternative,
+                  owner: &str) -> String {
+    format!("update-alternatives remove --name {} --target {} --owner {}",
+            quote(name), quote(&alternative.target().to_string_lossy()),
+            owner)
+}
+
+fn relative_to_root(path: &std::path::Path) -> &std::path::Path {
+    path.strip_prefix("/").unwrap_or(path)
+}
@@ -0,0 +1,56 @@
+// Copyright (c) 2018, Gregory Meyer
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Manages alternatives for executables, directories and environment
+//! variables, as the update-alternatives executable does. Groups of
+//! alternatives are read from and written to an `AlternativeDb`, and the
+//! alternative selected for each group can be chosen by a custom
+//! `selection::SelectionPolicy`.
+
+#[macro_use]
+extern crate serde_derive;
+
+pub mod alternative;
+pub mod alternative_db;
+pub mod alternative_list;
+pub mod clock;
+pub mod condition;
+pub mod constraint;
+pub mod discover;
+pub mod drop_in;
+pub mod elf;
+pub mod filesystem;
+pub mod hold;
+pub mod hook;
+pub mod overlay;
+pub mod profile;
+pub mod selection;
+pub mod settings;
+pub mod shim;
+pub mod validate;
+pub mod version;
+#[cfg(target_os = "linux")]
+pub mod watch;
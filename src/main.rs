@@ -26,127 +26,1252 @@
 
 #[macro_use]
 extern crate clap;
-#[macro_use]
-extern crate serde_derive;
+extern crate update_alternatives;
+
+use update_alternatives::*;
+use alternative::{Alternative, LinkKind};
+use alternative_db::AlternativeDb;
+use alternative_list::GroupKind;
+use condition::Condition;
+use constraint::Constraint;
+use hold::Hold;
+use version::VersionSource;
+use filesystem::LinkStrategy;
+use selection::Policy;
+use settings::TiePolicy;
+
+fn main() {
+    if let Some((name, list)) = shim::invoked_group("/etc/alternatives") {
+        shim::dispatch(&name, &list);
+    }
+
+    let matches = app().get_matches();
+
+    let root = root(&matches);
+    let folder = filesystem::under_root(root.as_deref(), "/etc/alternatives");
+
+    let mut db = match read_db(&folder) {
+        Ok(d) => d,
+        Err(_) => std::process::exit(1),
+    };
+
+    db.set_root(root);
+
+    if flag(&matches, "IGNORE_HOLDS") {
+        db.ignore_holds();
+    }
+
+    let expired = db.expire();
+
+    let mut mutated;
+
+    if let Some(list_matches) = matches.subcommand_matches("list") {
+        mutated = list(&db, list_matches);
+    } else if let Some(add_matches) = matches.subcommand_matches("add") {
+        mutated = add(&mut db, add_matches);
+    } else if let Some(remove_matches) = matches.subcommand_matches("remove") {
+        mutated = remove(&mut db, remove_matches);
+    } else if let Some(discover_matches) = matches.subcommand_matches("discover") {
+        mutated = discover(&mut db, discover_matches);
+    } else if let Some(sync_matches) = matches.subcommand_matches("sync") {
+        mutated = sync(&mut db, sync_matches);
+    } else if let Some(check_matches) = matches.subcommand_matches("check") {
+        mutated = check(&db, check_matches);
+    } else if matches.subcommand_matches("refresh").is_some() {
+        mutated = refresh(&mut db, &folder);
+    } else if let Some(watch_matches) = matches.subcommand_matches("watch") {
+        mutated = watch(&db, &folder, watch_matches);
+    } else if let Some(exec_matches) = matches.subcommand_matches("exec") {
+        mutated = exec(&db, exec_matches);
+    } else if let Some(shell_matches) = matches.subcommand_matches("shell") {
+        mutated = shell(&db, shell_matches);
+    } else if let Some(unshell_matches) = matches.subcommand_matches("unshell") {
+        mutated = unshell(unshell_matches);
+    } else if let Some(env_matches) = matches.subcommand_matches("env") {
+        mutated = env(&db, env_matches);
+    } else if let Some(shim_matches) = matches.subcommand_matches("shim") {
+        mutated = set_shim(&mut db, shim_matches);
+    } else if let Some(relocate_matches) = matches.subcommand_matches("relocate") {
+        mutated = relocate(&mut db, relocate_matches);
+    } else if let Some(constrain_matches) = matches.subcommand_matches("constrain") {
+        mutated = constrain(&mut db, constrain_matches);
+    } else if let Some(resolve_matches) = matches.subcommand_matches("resolve") {
+        mutated = resolve(&db, resolve_matches);
+    } else if let Some(set_matches) = matches.subcommand_matches("set") {
+        mutated = set(&mut db, set_matches);
+    } else if let Some(hold_matches) = matches.subcommand_matches("hold") {
+        mutated = hold(&mut db, hold_matches);
+    } else if let Some(unhold_matches) = matches.subcommand_matches("unhold") {
+        mutated = unhold(&mut db, unhold_matches);
+    } else if let Some(prefer_matches) = matches.subcommand_matches("prefer") {
+        mutated = prefer(&mut db, prefer_matches);
+    } else if let Some(configure_matches) = matches.subcommand_matches("configure") {
+        mutated = configure(&mut db, configure_matches);
+    } else if let Some(gen_hook_matches) = matches.subcommand_matches("gen-hook") {
+        mutated = gen_hook(&db, gen_hook_matches);
+    } else {
+        mutated = false;
+    }
+
+    if flag(&matches, "RESOLVE") && db.resolve_constraints() {
+        mutated = true;
+    }
+
+    if mutated {
+        if commit(&mut db, &folder).is_err() {
+            std::process::exit(1);
+        }
+    } else if expired {
+        // subcommands that only read should not fail without write access,
+        // so expired selections are only written out when possible
+        let _ = commit(&mut db, &folder);
+    }
+}
+
+// the value of --root, which may be given before or after the subcommand
+fn root(matches: &clap::ArgMatches) -> Option<std::path::PathBuf> {
+    let subcommand_root = matches.subcommand()
+        .1
+        .and_then(|m| m.value_of_os("ROOT"));
+
+    subcommand_root.or_else(|| matches.value_of_os("ROOT"))
+        .map(std::path::PathBuf::from)
+}
+
+// whether the global flag name was given before or after the subcommand
+fn flag(matches: &clap::ArgMatches, name: &str) -> bool {
+    let subcommand_flag = matches.subcommand()
+        .1
+        .is_some_and(|m| m.is_present(name));
+
+    subcommand_flag || matches.is_present(name)
+}
+
+fn read_db(folder: &std::path::Path) -> std::io::Result<AlternativeDb> {
+    match AlternativeDb::from_folder(folder) {
+        Ok(d) => {
+            eprintln!("update-alternatives: parsed {} alternatives",
+                      d.num_alternatives());
+
+            Ok(d)
+        },
+        Err(e) => {
+            eprintln!("update-alternatives: could not read folder {}: {}",
+                      folder.display(), e);
+
+            Err(e)
+        }
+    }
+}
+
+fn list(db: &AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    let name = matches.value_of("NAME").unwrap();
+
+    match db.alternatives(name) {
+        Some(alternatives) => {
+            print!("update-alternatives: {}", alternatives);
+
+            match db.chain(name) {
+                Ok(ref c) if c.len() > 2 => {
+                    println!("  resolves as {}", join_chain(c));
+                },
+                Ok(_) => (),
+                Err(e) => println!("  cannot be resolved: {}", e),
+            }
+        },
+        None => {
+            eprintln!("update-alternatives: no alternatives found for {}", name);
+        }
+    }
+
+    false
+}
+
+fn resolve(db: &AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    let name = matches.value_of("NAME").unwrap();
+
+    match db.chain(name) {
+        Ok(ref c) if c.len() > 1 => println!("{}", join_chain(c)),
+        Ok(_) => {
+            eprintln!("update-alternatives: {} has no usable alternative",
+                      name);
+
+            std::process::exit(1);
+        },
+        Err(e) => {
+            eprintln!("update-alternatives: could not resolve {}: {}", name,
+                      e);
+
+            std::process::exit(1);
+        },
+    }
+
+    false
+}
+
+fn join_chain(chain: &[std::path::PathBuf]) -> String {
+    let paths: Vec<String> = chain.iter()
+        .map(|p| p.display().to_string())
+        .collect();
+
+    paths.join(" -> ")
+}
+
+fn add(db: &mut AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    let target = matches.value_of("TARGET").unwrap();
+    let name = matches.value_of("NAME").unwrap();
+
+    let mut to_add = match matches.value_of("PRIORITY_FROM") {
+        Some(from) => {
+            let source = VersionSource::from_name(from).unwrap();
+            let on_disk = filesystem::under_root(db.root(), target);
+
+            match source.find(std::path::Path::new(target), &on_disk) {
+                Some(v) => Alternative::from_parts(target, 0)
+                    .with_version(source, v),
+                None => {
+                    eprintln!("update-alternatives: could not find the \
+                              version of {}", target);
+
+                    std::process::exit(1);
+                },
+            }
+        },
+        None => {
+            let weight_str = matches.value_of("WEIGHT").unwrap();
+
+            match weight_str.parse() {
+                Ok(w) => Alternative::from_parts(target, w),
+                Err(e) => {
+                    eprintln!("update-alternatives: could not parse {} as \
+                              weight: {}", weight_str, e);
+
+                    std::process::exit(1);
+                },
+            }
+        },
+    };
+
+    let priority = to_add.priority();
+
+    if let Some(owner) = matches.value_of("OWNER") {
+        to_add = to_add.owned_by(owner);
+    }
+
+    if matches.is_present("WRAPPER") {
+        to_add = to_add.with_kind(wrapper_kind(matches));
+    }
+
+    if let Some(tags) = matches.values_of("TAG") {
+        to_add = to_add.with_tags(tags.map(String::from));
+    }
+
+    if let Some(conditions) = matches.values_of("CONDITION") {
+        let parsed: Result<Vec<Condition>, String> = conditions
+            .map(str::parse)
+            .collect();
+
+        match parsed {
+            Ok(c) => to_add = to_add.with_conditions(c),
+            Err(e) => {
+                eprintln!("update-alternatives: could not parse condition: \
+                          {}", e);
+
+                std::process::exit(1);
+            },
+        }
+    }
+
+    let on_disk = filesystem::under_root(db.root(), target);
+
+    if let Some(problem) = validate::run_problem(std::path::Path::new(target),
+                                                 &on_disk, db.root()) {
+        if matches.is_present("STRICT") {
+            eprintln!("update-alternatives: not adding {}: {}", target,
+                      problem);
+
+            std::process::exit(1);
+        }
+
+        eprintln!("update-alternatives: warning: {}", problem);
+    }
+
+    if let Some(link) = matches.value_of("LINK") {
+        let existing = db.alternatives(name).map(|l| l.path().to_path_buf());
+
+        let result = match existing {
+            Some(ref p) if p != std::path::Path::new(link) => {
+                Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists,
+                                        format!("{} already links {}, use \
+                                                relocate to move it", name,
+                                                p.display())))
+            },
+            Some(_) => Ok(()),
+            None => db.add_group(name, link),
+        };
+
+        if let Err(e) = result {
+            eprintln!("update-alternatives: could not add {}: {}", name, e);
+
+            std::process::exit(1);
+        }
+    }
+
+    match db.add_alternative(name, to_add) {
+        Ok(true) => {
+            println!("update-alternatives: added alternative {} for {} with \
+                     priority {}", target, name, priority);
+
+            let chained = db.group_at(std::path::Path::new(target));
+
+            if let Some((other, _)) = chained {
+                println!("update-alternatives: {} is the link for {}, so {} \
+                         resolves through it", target, other, name);
+            }
+
+            true
+        },
+        Ok(false) => false,
+        Err(e) => {
+            eprintln!("update-alternatives: could not add {}: {}", name, e);
+
+            std::process::exit(1);
+        },
+    }
+}
+
+fn wrapper_kind(matches: &clap::ArgMatches) -> LinkKind {
+    let args = match matches.values_of("ARG") {
+        Some(a) => a.map(String::from).collect(),
+        None => Vec::new(),
+    };
+
+    let mut env = std::collections::BTreeMap::new();
+
+    for setting in matches.values_of("ENV").into_iter().flatten() {
+        match setting.find('=') {
+            Some(i) if filesystem::is_shell_identifier(&setting[..i]) => {
+                env.insert(setting[..i].to_string(),
+                           setting[i + 1..].to_string());
+            },
+            Some(i) => {
+                eprintln!("update-alternatives: {} is not a valid variable \
+                          name", &setting[..i]);
+
+                std::process::exit(1);
+            },
+            None => {
+                eprintln!("update-alternatives: {} is not of the form \
+                          VAR=VALUE", setting);
+
+                std::process::exit(1);
+            }
+        }
+    }
+
+    LinkKind::Wrapper{ args, env }
+}
+
+fn remove(db: &mut AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    let target = matches.value_of("TARGET");
+    let name = matches.value_of("NAME");
+
+    if let Some(owner) = matches.value_of("OWNER") {
+        return db.remove_owner(owner, name, target.map(std::path::Path::new));
+    }
+
+    let (target, name) = (target.unwrap(), name.unwrap());
+
+    if db.remove_alternative(name, target) {
+        println!("update-alternatives: removed alternative {} for {}",
+                 target, name);
+
+        return true;
+    }
+
+    false
+}
+
+fn discover(db: &mut AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    let name = matches.value_of("NAME").unwrap();
+    let patterns: Vec<&str> = matches.values_of("PATTERN").unwrap().collect();
+
+    let directories: Vec<std::path::PathBuf> =
+        match matches.values_of_os("DIRECTORY") {
+        Some(d) => d.map(std::path::PathBuf::from).collect(),
+        None => std::env::var_os("PATH")
+            .map(|p| std::env::split_paths(&p).collect())
+            .unwrap_or_default(),
+    };
+
+    let weight: Option<i32> = match matches.value_of("WEIGHT") {
+        Some(w) => match w.parse() {
+            Ok(w) => Some(w),
+            Err(e) => {
+                eprintln!("update-alternatives: could not parse {} as \
+                          weight: {}", w, e);
+
+                std::process::exit(1);
+            },
+        },
+        None => None,
+    };
+
+    let candidates = discover::discover(db, &patterns, &directories);
+
+    if candidates.is_empty() {
+        eprintln!("update-alternatives: no candidates found for {}", name);
+
+        return false;
+    }
+
+    let register = matches.is_present("REGISTER");
+    let mut mutated = false;
+
+    for candidate in candidates {
+        let target = candidate.target();
+        let priority = weight.or_else(|| {
+            candidate.version().map(|v| v.priority())
+        });
+
+        let priority = match (candidate.version(), priority) {
+            (Some(v), Some(p)) => {
+                println!("update-alternatives: found {} (version {}, priority \
+                         {})", target.display(), v, p);
+
+                p
+            },
+            (None, Some(p)) => {
+                println!("update-alternatives: found {} (priority {})",
+                         target.display(), p);
+
+                p
+            },
+            (_, None) => {
+                println!("update-alternatives: found {} (no version in its \
+                         file name, pass --weight to register it)",
+                         target.display());
+
+                continue;
+            },
+        };
+
+        if !register {
+            continue;
+        }
+
+        let to_add = match (weight, candidate.version()) {
+            (None, Some(v)) => Alternative::from_parts(target, priority)
+                .with_version(VersionSource::FileName, v.clone()),
+            _ => Alternative::from_parts(target, priority),
+        };
+
+        match db.add_alternative(name, to_add) {
+            Ok(true) => {
+                println!("update-alternatives: added alternative {} for {} \
+                         with priority {}", target.display(), name, priority);
+                mutated = true;
+            },
+            Ok(false) => (),
+            Err(e) => {
+                eprintln!("update-alternatives: could not add {}: {}", name, e);
+
+                std::process::exit(1);
+            },
+        }
+    }
+
+    mutated
+}
+
+fn sync(db: &mut AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    let directory = filesystem::under_root(db.root(),
+                                           matches.value_of("DIRECTORY")
+                                                  .unwrap());
+
+    match db.sync(&directory) {
+        Ok(mutated) => mutated,
+        Err(e) => {
+            eprintln!("update-alternatives: could not read drop-ins from {}: \
+                      {}", directory.display(), e);
+
+            std::process::exit(1);
+        }
+    }
+}
+
+fn check(db: &AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    let strict = matches.is_present("STRICT");
+    let mut num_problems = 0;
+    let mut num_unrunnable = 0;
+
+    for (name, list) in db.lists() {
+        for problem in list.check() {
+            println!("update-alternatives: {}: {}", name, problem);
+            num_problems += 1;
+        }
+
+        for problem in list.run_problems() {
+            println!("update-alternatives: {}: warning: {}", name, problem);
+            num_unrunnable += 1;
+        }
+    }
+
+    for problem in profile::check(db) {
+        println!("update-alternatives: {}", problem);
+        num_problems += 1;
+    }
+
+    if num_problems > 0 {
+        eprintln!("update-alternatives: found {} problems, run refresh to \
+                  repair them", num_problems);
+
+        std::process::exit(1);
+    }
+
+    if strict && num_unrunnable > 0 {
+        eprintln!("update-alternatives: found {} alternatives that cannot run \
+                  on this host", num_unrunnable);
+
+        std::process::exit(1);
+    }
+
+    println!("update-alternatives: all {} alternatives are up to date",
+             db.num_alternatives());
+
+    false
+}
+
+fn refresh(db: &mut AlternativeDb, folder: &std::path::Path) -> bool {
+    if db.rederive_priorities() && write_out(db, folder).is_err() {
+        std::process::exit(1);
+    }
+
+    if write_links(db, folder).is_err() {
+        std::process::exit(1);
+    }
+
+    false
+}
+
+#[cfg(target_os = "linux")]
+fn watch(db: &AlternativeDb, folder: &std::path::Path,
+         matches: &clap::ArgMatches) -> bool {
+    let debounce_str = matches.value_of("DEBOUNCE").unwrap();
+
+    let debounce: u64 = match debounce_str.parse() {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("update-alternatives: could not parse {} as \
+                      milliseconds: {}", debounce_str, e);
+
+            std::process::exit(1);
+        },
+    };
+
+    if let Err(e) = watch::watch(folder, db.root(),
+                                 std::time::Duration::from_millis(debounce)) {
+        eprintln!("update-alternatives: could not watch alternatives: {}", e);
+
+        std::process::exit(1);
+    }
+
+    false
+}
+
+#[cfg(not(target_os = "linux"))]
+fn watch(_: &AlternativeDb, _: &std::path::Path,
+         _: &clap::ArgMatches) -> bool {
+    eprintln!("update-alternatives: watch is only supported on Linux");
+
+    std::process::exit(1);
+}
+
+fn exec(db: &AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    let selections: Vec<&str> = matches.values_of("USE").unwrap().collect();
+    let command: Vec<std::ffi::OsString> = matches.values_of_os("COMMAND")
+        .unwrap()
+        .map(|c| c.to_os_string())
+        .collect();
+
+    let link_dir = match overlay::LinkDir::create(db, &selections, "exec") {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("update-alternatives: could not create links: {}", e);
+
+            std::process::exit(1);
+        }
+    };
+
+    let code = match overlay::run(&link_dir, &command) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("update-alternatives: could not run {}: {}",
+                      command[0].to_string_lossy(), e);
+
+            127
+        }
+    };
+
+    drop(link_dir);
+    std::process::exit(code);
+}
+
+fn shell(db: &AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    let selections: Vec<&str> = matches.values_of("USE").unwrap().collect();
+    let shell = session_shell(matches);
+
+    let snippet = match overlay::LinkDir::session() {
+        Some(ref session) if session.path().is_dir() => {
+            session.update(db, &selections).map(|_| shell.rehash())
+        },
+        _ => overlay::LinkDir::create(db, &selections, "shell").map(|mut d| {
+            d.keep();
+
+            shell.export(overlay::SESSION_VAR, d.path())
+                + &shell.prepend_path(d.path()) + &shell.rehash()
+        }),
+    };
+
+    match snippet {
+        Ok(s) => print!("{}", s),
+        Err(e) => {
+            eprintln!("update-alternatives: could not create links: {}", e);
+
+            std::process::exit(1);
+        }
+    }
+
+    false
+}
+
+fn unshell(matches: &clap::ArgMatches) -> bool {
+    let shell = session_shell(matches);
+
+    let session = match overlay::LinkDir::session() {
+        Some(s) => s,
+        None => {
+            eprintln!("update-alternatives: no shell session is active");
+
+            std::process::exit(1);
+        }
+    };
+
+    let paths: Vec<std::path::PathBuf> = match std::env::var_os("PATH") {
+        Some(p) => std::env::split_paths(&p)
+            .filter(|p| p != session.path())
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let snippet = match shell.set_path(&paths) {
+        Ok(s) => s + &shell.unset(overlay::SESSION_VAR) + &shell.rehash(),
+        Err(e) => {
+            eprintln!("update-alternatives: could not restore PATH: {}", e);
+
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(e) = session.remove() {
+        eprintln!("update-alternatives: could not remove shell session: {}", e);
+
+        std::process::exit(1);
+    }
+
+    print!("{}", snippet);
+
+    false
+}
+
+fn env(db: &AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    print!("{}", profile::script(db, session_shell(matches)));
+
+    false
+}
+
+fn session_shell(matches: &clap::ArgMatches) -> overlay::Shell {
+    match matches.value_of("SHELL") {
+        Some(s) => overlay::Shell::from_name(s),
+        None => overlay::Shell::from_name(&std::env::var("SHELL")
+                                              .unwrap_or_default()),
+    }
+}
+
+fn set_shim(db: &mut AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    let name = matches.value_of("NAME").unwrap();
+    let enable = !matches.is_present("DISABLE");
+
+    let list = match db.alternatives_mut(name) {
+        Some(l) => l,
+        None => {
+            eprintln!("update-alternatives: no alternatives found for {}", name);
+
+            std::process::exit(1);
+        }
+    };
+
+    if !list.set_shim(enable) {
+        return false;
+    }
+
+    if enable {
+        println!("update-alternatives: {} is now dispatched by a shim", name);
+    } else {
+        println!("update-alternatives: {} is now a plain symlink", name);
+    }
+
+    true
+}
+
+fn relocate(db: &mut AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    let name = matches.value_of("NAME").unwrap();
+    let link = matches.value_of("LINK").unwrap();
+
+    match db.relocate(name, link) {
+        Ok(true) => {
+            println!("update-alternatives: the link for {} is now {}", name,
+                     link);
+
+            true
+        },
+        Ok(false) => false,
+        Err(e) => {
+            eprintln!("update-alternatives: could not relocate {}: {}", name,
+                      e);
+
+            std::process::exit(1);
+        },
+    }
+}
+
+fn constrain(db: &mut AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    let name = matches.value_of("NAME").unwrap();
+    let when = matches.value_of("WHEN").unwrap();
+    let requires = matches.value_of("REQUIRES").unwrap();
+    let under = matches.value_of("UNDER").unwrap_or(when);
+
+    if db.alternatives(requires).is_none() {
+        eprintln!("update-alternatives: no alternatives found for {}",
+                  requires);
+
+        std::process::exit(1);
+    }
+
+    let list = match db.alternatives_mut(name) {
+        Some(l) => l,
+        None => {
+            eprintln!("update-alternatives: no alternatives found for {}", name);
+
+            std::process::exit(1);
+        }
+    };
+
+    let constraint = Constraint::new(when, requires, under);
+
+    if matches.is_present("REMOVE") {
+        if !list.remove_constraint(&constraint) {
+            return false;
+        }
+
+        println!("update-alternatives: {} no longer requires that {}", name,
+                 constraint);
+    } else {
+        if !list.add_constraint(constraint.clone()) {
+            return false;
+        }
+
+        println!("update-alternatives: {} now requires that {}", name,
+                 constraint);
+    }
+
+    true
+}
+
+fn set(db: &mut AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    let name = matches.value_of("NAME").unwrap();
+
+    let seconds = matches.value_of("FOR").map(|d| {
+        match clock::parse_duration(d) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("update-alternatives: could not parse duration: {}",
+                          e);
+
+                std::process::exit(1);
+            },
+        }
+    });
+
+    let list = match db.alternatives_mut(name) {
+        Some(l) => l,
+        None => {
+            eprintln!("update-alternatives: no alternatives found for {}", name);
+
+            std::process::exit(1);
+        }
+    };
+
+    let value = match matches.value_of("TARGET") {
+        Some(v) => v,
+        None => {
+            if !list.set_manual(None) {
+                return false;
+            }
+
+            println!("update-alternatives: {} is now selected automatically",
+                     name);
+
+            return true;
+        },
+    };
+
+    let target = match list.find(value) {
+        Some(a) => match list.ineligible_reason(a) {
+            Some(reason) => {
+                eprintln!("update-alternatives: {} cannot be used for {}: {}",
+                          value, name, reason);
+
+                std::process::exit(1);
+            },
+            None => a.target().to_path_buf(),
+        },
+        None => {
+            eprintln!("update-alternatives: {} is not an alternative for {}",
+                      value, name);
+
+            std::process::exit(1);
+        },
+    };
+
+    match seconds {
+        Some(s) => {
+            println!("update-alternatives: {} now uses {} for {}", name,
+                     target.display(), clock::format_duration(s));
+            list.set_manual_for(target, s);
+
+            true
+        },
+        None => {
+            if !list.set_manual(Some(target.clone())) {
+                return false;
+            }
+
+            println!("update-alternatives: {} now uses {}", name,
+                     target.display());
+
+            true
+        },
+    }
+}
+
+fn hold(db: &mut AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    let name = matches.value_of("NAME").unwrap();
+    let now = db.now();
+
+    let list = match db.alternatives_mut(name) {
+        Some(l) => l,
+        None => {
+            eprintln!("update-alternatives: no alternatives found for {}", name);
+
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(existing) = list.hold() {
+        eprintln!("update-alternatives: {} is already {}", name, existing);
+
+        return false;
+    }
+
+    let target = list.select().map(|a| a.target().to_path_buf());
+    let hold = Hold::new(target.as_deref(), matches.value_of("REASON"), now);
+
+    let reason = hold.reason()
+        .map(|r| format!(": {}", r))
+        .unwrap_or_default();
+
+    match target {
+        Some(ref t) => println!("update-alternatives: {} is now held at {} by \
+                                {}{}", name, t.display(), hold.by(), reason),
+        None => println!("update-alternatives: {} is now {}", name, hold),
+    }
+
+    list.set_hold(Some(hold))
+}
+
+fn unhold(db: &mut AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    let name = matches.value_of("NAME").unwrap();
+
+    let list = match db.alternatives_mut(name) {
+        Some(l) => l,
+        None => {
+            eprintln!("update-alternatives: no alternatives found for {}", name);
+
+            std::process::exit(1);
+        }
+    };
+
+    let released = match list.hold() {
+        Some(h) => h.clone(),
+        None => return false,
+    };
+
+    list.set_hold(None);
+
+    println!("update-alternatives: {} is no longer {}", name, released);
+
+    true
+}
+
+fn prefer(db: &mut AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    let tags: Vec<String> = matches.values_of("TAG")
+        .unwrap()
+        .map(String::from)
+        .collect();
+
+    let selected = |db: &AlternativeDb| -> Vec<(String, Option<String>)> {
+        db.lists()
+            .map(|(name, list)| {
+                let target = list.select()
+                    .map(|a| a.target().display().to_string());
+
+                (name.to_string(), target)
+            })
+            .collect()
+    };
+
+    let before = selected(db);
+
+    if !db.configure(|s| s.set_preferred_tags(tags.clone())) {
+        return false;
+    }
+
+    println!("update-alternatives: now preferring {}", tags.join(", "));
+
+    for ((name, previous), (_, current)) in before.iter().zip(selected(db)) {
+        if *previous == current {
+            continue;
+        }
+
+        match current {
+            Some(t) => println!("update-alternatives: {} now uses {}", name, t),
+            None => println!("update-alternatives: {} no longer has a usable \
+                             alternative", name),
+        }
+    }
 
-mod alternative;
-mod alternative_db;
-mod alternative_list;
-mod filesystem;
+    true
+}
 
-use alternative::Alternative;
-use alternative_db::AlternativeDb;
+// parses a comma separated list of tags
+fn parse_tags(value: &str) -> Vec<String> {
+    value.split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(String::from)
+        .collect()
+}
 
-fn main() {
-    let matches = app().get_matches();
+fn configure(db: &mut AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    let strategy = matches.value_of("LINK_STRATEGY")
+        .and_then(LinkStrategy::from_name);
+    let tie_policy = matches.value_of("TIE_POLICY")
+        .and_then(TiePolicy::from_name);
+    let preferred_tags = match matches.value_of("PREFERRED_TAGS") {
+        Some("default") | None => None,
+        Some(tags) => Some(parse_tags(tags)),
+    };
 
-    let mut db = match read_db("/etc/alternatives") {
-        Ok(d) => d,
-        Err(_) => std::process::exit(1),
+    let name = match matches.value_of("NAME") {
+        Some(n) => n,
+        None => {
+            let mut changed = false;
+
+            if matches.is_present("LINK_STRATEGY") {
+                let strategy = strategy.unwrap_or_default();
+
+                if db.configure(|s| s.set_link_strategy(strategy)) {
+                    println!("update-alternatives: the global link strategy \
+                             is now {}", strategy);
+
+                    changed = true;
+                }
+            }
+
+            if matches.is_present("TIE_POLICY") {
+                let policy = tie_policy.unwrap_or_default();
+
+                if db.configure(|s| s.set_tie_policy(policy)) {
+                    println!("update-alternatives: ties are now broken by the \
+                             {} policy globally", policy);
+
+                    changed = true;
+                }
+            }
+
+            if matches.is_present("PREFERRED_TAGS") {
+                let tags = preferred_tags.unwrap_or_default();
+
+                if db.configure(|s| s.set_preferred_tags(tags.clone())) {
+                    if tags.is_empty() {
+                        println!("update-alternatives: no tags are preferred \
+                                 globally");
+                    } else {
+                        println!("update-alternatives: {} are now preferred \
+                                 globally", tags.join(", "));
+                    }
+
+                    changed = true;
+                }
+            }
+
+            return changed;
+        },
     };
 
-    let mutated;
+    let list = match db.alternatives_mut(name) {
+        Some(l) => l,
+        None => {
+            eprintln!("update-alternatives: no alternatives found for {}", name);
 
-    if let Some(list_matches) = matches.subcommand_matches("list") {
-        mutated = list(&db, list_matches);
-    } else if let Some(add_matches) = matches.subcommand_matches("add") {
-        mutated = add(&mut db, add_matches);
-    } else if let Some(remove_matches) = matches.subcommand_matches("remove") {
-        mutated = remove(&mut db, remove_matches);
-    } else {
-        mutated = false;
+            std::process::exit(1);
+        }
+    };
+
+    let mut changed = false;
+
+    if matches.is_present("LINK_STRATEGY") && list.set_link_strategy(strategy) {
+        match strategy {
+            Some(s) => println!("update-alternatives: {} is now installed as \
+                                a {}", name, s),
+            None => println!("update-alternatives: {} is now installed as a \
+                             {}, as configured globally", name,
+                             list.link_strategy()),
+        }
+
+        changed = true;
     }
 
-    if mutated && commit(&db).is_err() {
-        std::process::exit(1);
+    let policy = matches.value_of("POLICY").and_then(Policy::from_name);
+
+    if let Some(policy) = policy {
+        if list.set_policy(policy) {
+            println!("update-alternatives: {} is now selected by {}", name,
+                     policy);
+
+            changed = true;
+        }
     }
-}
 
-fn read_db<P: std::convert::AsRef<std::path::Path>>(path: P)
--> std::io::Result<AlternativeDb> {
-    match AlternativeDb::from_folder(path) {
-        Ok(d) => {
-            println!("update-alternatives: parsed {} alternatives",
-                     d.num_alternatives());
+    if matches.is_present("TIE_POLICY") && list.set_tie_policy(tie_policy) {
+        match tie_policy {
+            Some(p) => println!("update-alternatives: ties for {} are now \
+                                broken by the {} policy", name, p),
+            None => println!("update-alternatives: ties for {} are now broken \
+                             by the {} policy, as configured globally", name,
+                             list.tie_policy()),
+        }
 
-            Ok(d)
-        },
-        Err(e) => {
-            eprintln!("update-alternatives: could not read folder \
-                      /etc/alternatives: {}", e);
+        changed = true;
+    }
 
-            Err(e)
+    if matches.is_present("PREFERRED_TAGS")
+        && list.set_preferred_tags(preferred_tags.clone()) {
+        match preferred_tags {
+            Some(ref t) if t.is_empty() => println!("update-alternatives: {} \
+                                                    prefers no tags", name),
+            Some(ref t) => println!("update-alternatives: {} now prefers {}",
+                                    name, t.join(", ")),
+            None => println!("update-alternatives: {} now prefers the tags \
+                             preferred globally", name),
         }
+
+        changed = true;
     }
-}
 
-fn list(db: &AlternativeDb, matches: &clap::ArgMatches) -> bool {
-    let name = matches.value_of("NAME").unwrap();
+    let kind = match matches.value_of("KIND") {
+        Some("variable") => {
+            let variable = matches.value_of("VARIABLE").unwrap();
 
-    match db.alternatives(name) {
-        Some(alternatives) => {
-            print!("update-alternatives: {}", alternatives);
+            if !filesystem::is_shell_identifier(variable) {
+                eprintln!("update-alternatives: {} is not a valid variable \
+                          name", variable);
+
+                std::process::exit(1);
+            }
+
+            Some(GroupKind::Variable{ name: variable.to_string() })
         },
-        None => {
-            eprintln!("update-alternatives: no alternatives found for {}", name);
+        Some("directory") => Some(GroupKind::Directory),
+        Some(_) => Some(GroupKind::Link),
+        None => None,
+    };
+
+    if let Some(kind) = kind {
+        if list.set_kind(kind) {
+            match *list.kind() {
+                GroupKind::Link => println!("update-alternatives: {} is now a \
+                                            link to an executable", name),
+                GroupKind::Directory => println!("update-alternatives: {} is \
+                                                 now a link to a directory",
+                                                 name),
+                GroupKind::Variable{ name: ref variable } => {
+                    println!("update-alternatives: {} is now exported as {}",
+                             name, variable);
+                },
+            }
+
+            changed = true;
         }
     }
 
-    false
+    changed
 }
 
-fn add(db: &mut AlternativeDb, matches: &clap::ArgMatches) -> bool {
-    let target = matches.value_of("TARGET").unwrap();
-    let name = matches.value_of("NAME").unwrap();
-    let weight_str = matches.value_of("WEIGHT").unwrap();
+fn gen_hook(db: &AlternativeDb, matches: &clap::ArgMatches) -> bool {
+    let format = matches.value_of("FORMAT").unwrap();
+    let directory = matches.value_of("DIRECTORY").unwrap();
+    let target = matches.value_of("TARGET").map(std::path::Path::new);
 
-    let weight: i32 = match weight_str.parse() {
-        Ok(w) => w,
-        Err(e) => {
-            eprintln!("update-alternatives: could not parse {} as \
-                      weight: {}", weight_str, e);
+    let group = matches.value_of("NAME").map(|name| {
+        match db.alternatives(name) {
+            Some(list) => (name, list),
+            None => {
+                eprintln!("update-alternatives: no alternatives found for {}",
+                          name);
+
+                std::process::exit(1);
+            }
+        }
+    });
+
+    let hook = match (format, group) {
+        ("alpm", _) => {
+            let executable = match std::env::current_exe() {
+                Ok(e) => e,
+                Err(e) => {
+                    eprintln!("update-alternatives: could not locate the \
+                              update-alternatives executable: {}", e);
+
+                    std::process::exit(1);
+                }
+            };
+
+            hook::alpm(&executable, std::path::Path::new(directory), group)
+        },
+        ("rpm", Some((name, list))) => {
+            generated(hook::rpm(name, list, target))
+        },
+        ("deb", Some((name, list))) => {
+            generated(hook::deb(name, list, target))
+        },
+        (_, None) => {
+            eprintln!("update-alternatives: --name is required for {} hooks",
+                      format);
 
             std::process::exit(1);
         },
+        _ => unreachable!(),
     };
 
-    if db.add_alternative(name, Alternative::from_parts(target, weight)) {
-        println!("update-alternatives: added alternative {} for {} with \
-                 priority {}", target, name, weight);
-
-        return true;
-    }
+    print!("{}", hook);
 
     false
 }
 
-fn remove(db: &mut AlternativeDb, matches: &clap::ArgMatches) -> bool {
-    let target = matches.value_of("TARGET").unwrap();
-    let name = matches.value_of("NAME").unwrap();
+fn generated(hook: Result<String, String>) -> String {
+    match hook {
+        Ok(h) => h,
+        Err(e) => {
+            eprintln!("update-alternatives: cannot generate a hook, as {}", e);
 
-    if db.remove_alternative(name, target) {
-        println!("update-alternatives: removed alternative {} for {}",
-                 target, name);
+            std::process::exit(1);
+        },
+    }
+}
 
-        return true;
+fn commit(db: &mut AlternativeDb,
+          folder: &std::path::Path) -> std::io::Result<()> {
+    // subcommands may have changed selections through alternatives_mut
+    db.link_chains();
+
+    let ties: Vec<String> = db.lists()
+        .filter_map(|(_, l)| l.tie_problem())
+        .collect();
+
+    if !ties.is_empty() {
+        for tie in ties.iter() {
+            eprintln!("update-alternatives: {}", tie);
+        }
+
+        eprintln!("update-alternatives: not saving changes, as the tie policy \
+                  forbids ties");
+
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput,
+                                       ties.join("; ")));
     }
 
-    false
+    let violations: Vec<String> = db.violations()
+        .into_iter()
+        .map(|(name, c)| format!("{}: {}", name, c))
+        .collect();
+
+    if !violations.is_empty() {
+        for violation in violations.iter() {
+            eprintln!("update-alternatives: {}", violation);
+        }
+
+        eprintln!("update-alternatives: not saving changes, as they violate \
+                  constraints. pass --resolve to select targets that satisfy \
+                  them");
+
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput,
+                                       violations.join("; ")));
+    }
+
+    write_out(db, folder)?;
+
+    write_links(db, folder)
 }
 
-fn commit(db: &AlternativeDb) -> std::io::Result<()> {
-    if let Err(e) = db.write_out("/etc/alternatives") {
-        eprintln!("update-alternatives: could not commit changes to \
-                  /etc/alternatives: {}", e);
+// writes the links and profile scripts, then the database again if the
+// hashes of any hardlinks or copies changed or a link was moved
+fn write_links(db: &mut AlternativeDb,
+               folder: &std::path::Path) -> std::io::Result<()> {
+    let record_changed = match db.write_links() {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("update-alternatives: could not write symlinks: {}", e);
+
+            return Err(e);
+        },
+    };
 
-        Err(e)
-    } else if let Err(e) = db.write_links() {
-        eprintln!("update-alternatives: could not write symlinks: {}", e);
+    if let Err(e) = profile::write(db) {
+        eprintln!("update-alternatives: could not write profile scripts: {}",
+                  e);
 
-        Err(e)
-    } else {
-        Ok(())
+        return Err(e);
+    }
+
+    if record_changed {
+        write_out(db, folder)?;
+    }
+
+    Ok(())
+}
+
+fn write_out(db: &AlternativeDb,
+             folder: &std::path::Path) -> std::io::Result<()> {
+    if let Err(e) = db.write_out(folder) {
+        eprintln!("update-alternatives: could not commit changes to {}: {}",
+                  folder.display(), e);
+
+        return Err(e);
     }
+
+    Ok(())
 }
 
 fn app<'a, 'b>() -> clap::App<'a, 'b> {
@@ -154,6 +1279,23 @@ fn app<'a, 'b>() -> clap::App<'a, 'b> {
         .version(crate_version!())
         .author("Gregory Meyer <gregjm@umich.edu>")
         .about(ABOUT)
+        .arg(clap::Arg::with_name("ROOT")
+                 .help("Manage the tree mounted at <ROOT> instead of /, such \
+                       as an image that is being built")
+                 .value_name("ROOT")
+                 .long("root")
+                 .global(true)
+                 .takes_value(true))
+        .arg(clap::Arg::with_name("RESOLVE")
+                 .help("Select targets by hand in the groups that violated \
+                       constraints require, instead of refusing changes")
+                 .long("resolve")
+                 .global(true))
+        .arg(clap::Arg::with_name("IGNORE_HOLDS")
+                 .help("Change the links of held groups as if they were not \
+                       held")
+                 .long("ignore-holds")
+                 .global(true))
         .subcommand(clap::SubCommand::with_name("list")
                         .about(LIST_ABOUT)
                         .arg(clap::Arg::with_name("NAME")
@@ -184,7 +1326,76 @@ fn app<'a, 'b>() -> clap::App<'a, 'b> {
                                  .value_name("WEIGHT")
                                  .short("w")
                                  .long("weight")
-                                 .required(true)
+                                 .required_unless("PRIORITY_FROM")
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("PRIORITY_FROM")
+                                 .help("Derive the priority from the version \
+                                       in the file name of <TARGET>, or in \
+                                       the output of <TARGET> --version")
+                                 .value_name("SOURCE")
+                                 .long("priority-from")
+                                 .possible_values(&["version",
+                                                    "version-output"])
+                                 .conflicts_with("WEIGHT")
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("OWNER")
+                                 .help("The package or tool registering the \
+                                       alternative")
+                                 .value_name("OWNER")
+                                 .short("o")
+                                 .long("owner")
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("TAG")
+                                 .help("A label for the alternative, such as \
+                                       llvm or lts")
+                                 .value_name("TAG")
+                                 .long("tag")
+                                 .multiple(true)
+                                 .number_of_values(1)
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("STRICT")
+                                 .help("Refuse to add <TARGET> if it cannot \
+                                       run on this host")
+                                 .long("strict"))
+                        .arg(clap::Arg::with_name("CONDITION")
+                                 .help("A condition that must hold for the \
+                                       alternative to be selected: arch, \
+                                       path=PATH, cpu=FLAG, env=NAME or \
+                                       env=NAME=VALUE")
+                                 .value_name("CONDITION")
+                                 .long("condition")
+                                 .multiple(true)
+                                 .number_of_values(1)
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("LINK")
+                                 .help("The path of the link, if the \
+                                       alternative is the first for <NAME>")
+                                 .value_name("LINK")
+                                 .short("l")
+                                 .long("link")
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("WRAPPER")
+                                 .help("Generate a wrapper script instead of \
+                                       a symlink")
+                                 .long("wrapper"))
+                        .arg(clap::Arg::with_name("ARG")
+                                 .help("An argument the wrapper passes \
+                                       before any others")
+                                 .value_name("ARG")
+                                 .long("arg")
+                                 .multiple(true)
+                                 .number_of_values(1)
+                                 .allow_hyphen_values(true)
+                                 .requires("WRAPPER")
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("ENV")
+                                 .help("An environment variable the wrapper \
+                                       sets")
+                                 .value_name("VAR=VALUE")
+                                 .long("env")
+                                 .multiple(true)
+                                 .number_of_values(1)
+                                 .requires("WRAPPER")
                                  .takes_value(true)))
         .subcommand(clap::SubCommand::with_name("remove")
                         .about(REMOVE_ABOUT)
@@ -194,35 +1405,589 @@ fn app<'a, 'b>() -> clap::App<'a, 'b> {
                                  .value_name("TARGET")
                                  .short("t")
                                  .long("target")
-                                 .required(true)
+                                 .required_unless("OWNER")
                                  .takes_value(true))
                         .arg(clap::Arg::with_name("NAME")
                                  .help("The name of the alternative to remove")
                                  .value_name("NAME")
                                  .short("n")
                                  .long("name")
+                                 .required_unless("OWNER")
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("OWNER")
+                                 .help("Release this owner's references \
+                                       instead of removing outright")
+                                 .value_name("OWNER")
+                                 .short("o")
+                                 .long("owner")
+                                 .takes_value(true)))
+        .subcommand(clap::SubCommand::with_name("discover")
+                        .about(DISCOVER_ABOUT)
+                        .arg(clap::Arg::with_name("NAME")
+                                 .help("The name of the alternatives to \
+                                       discover")
+                                 .value_name("NAME")
+                                 .short("n")
+                                 .long("name")
+                                 .required(true)
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("PATTERN")
+                                 .help("A file name pattern to match, where * \
+                                       matches anything and ? any one \
+                                       character")
+                                 .value_name("PATTERN")
+                                 .short("p")
+                                 .long("pattern")
+                                 .required(true)
+                                 .multiple(true)
+                                 .number_of_values(1)
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("DIRECTORY")
+                                 .help("A directory to scan instead of PATH")
+                                 .value_name("DIRECTORY")
+                                 .short("d")
+                                 .long("directory")
+                                 .multiple(true)
+                                 .number_of_values(1)
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("WEIGHT")
+                                 .help("The priority to register every match \
+                                       with, instead of one derived from its \
+                                       version")
+                                 .value_name("WEIGHT")
+                                 .short("w")
+                                 .long("weight")
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("REGISTER")
+                                 .help("Add every match as an alternative")
+                                 .short("r")
+                                 .long("register")))
+        .subcommand(clap::SubCommand::with_name("sync")
+                        .about(SYNC_ABOUT)
+                        .arg(clap::Arg::with_name("DIRECTORY")
+                                 .help("The directory to read drop-ins from")
+                                 .value_name("DIRECTORY")
+                                 .short("d")
+                                 .long("directory")
+                                 .default_value("/usr/share/\
+                                                update-alternatives.d")
+                                 .takes_value(true)))
+        .subcommand(clap::SubCommand::with_name("check")
+                        .about(CHECK_ABOUT)
+                        .arg(clap::Arg::with_name("STRICT")
+                                 .help("Exit unsuccessfully if any \
+                                       alternative cannot run on this host")
+                                 .long("strict")))
+        .subcommand(clap::SubCommand::with_name("refresh")
+                        .about(REFRESH_ABOUT))
+        .subcommand(clap::SubCommand::with_name("watch")
+                        .about(WATCH_ABOUT)
+                        .arg(clap::Arg::with_name("DEBOUNCE")
+                                 .help("How long to wait for further changes \
+                                       before re-evaluating, in milliseconds")
+                                 .value_name("DEBOUNCE")
+                                 .short("d")
+                                 .long("debounce")
+                                 .default_value("500")
+                                 .takes_value(true)))
+        .subcommand(clap::SubCommand::with_name("exec")
+                        .about(EXEC_ABOUT)
+                        .arg(clap::Arg::with_name("USE")
+                                 .help("A selection of the form NAME=TARGET, \
+                                       where TARGET is the path or file name \
+                                       of an alternative for NAME")
+                                 .value_name("NAME=TARGET")
+                                 .short("u")
+                                 .long("use")
+                                 .required(true)
+                                 .multiple(true)
+                                 .number_of_values(1)
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("COMMAND")
+                                 .help("The command to run")
+                                 .value_name("COMMAND")
+                                 .required(true)
+                                 .multiple(true)
+                                 .last(true)))
+        .subcommand(clap::SubCommand::with_name("shell")
+                        .about(SHELL_ABOUT)
+                        .arg(clap::Arg::with_name("USE")
+                                 .help("A selection of the form NAME=TARGET, \
+                                       where TARGET is the path or file name \
+                                       of an alternative for NAME")
+                                 .value_name("NAME=TARGET")
+                                 .short("u")
+                                 .long("use")
+                                 .required(true)
+                                 .multiple(true)
+                                 .number_of_values(1)
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("SHELL")
+                                 .help("The shell to print commands for, \
+                                       instead of the one in $SHELL")
+                                 .value_name("SHELL")
+                                 .short("s")
+                                 .long("shell")
+                                 .possible_values(&["sh", "bash", "zsh",
+                                                    "fish"])
+                                 .takes_value(true)))
+        .subcommand(clap::SubCommand::with_name("unshell")
+                        .about(UNSHELL_ABOUT)
+                        .arg(clap::Arg::with_name("SHELL")
+                                 .help("The shell to print commands for, \
+                                       instead of the one in $SHELL")
+                                 .value_name("SHELL")
+                                 .short("s")
+                                 .long("shell")
+                                 .possible_values(&["sh", "bash", "zsh",
+                                                    "fish"])
+                                 .takes_value(true)))
+        .subcommand(clap::SubCommand::with_name("shim")
+                        .about(SHIM_ABOUT)
+                        .arg(clap::Arg::with_name("NAME")
+                                 .help("The name of the alternatives to \
+                                       dispatch with a shim")
+                                 .value_name("NAME")
+                                 .short("n")
+                                 .long("name")
+                                 .required(true)
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("DISABLE")
+                                 .help("Go back to a plain symlink")
+                                 .short("d")
+                                 .long("disable")))
+        .subcommand(clap::SubCommand::with_name("relocate")
+                        .about(RELOCATE_ABOUT)
+                        .arg(clap::Arg::with_name("NAME")
+                                 .help("The name of the alternatives to move")
+                                 .value_name("NAME")
+                                 .short("n")
+                                 .long("name")
+                                 .required(true)
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("LINK")
+                                 .help("The new path of the link")
+                                 .value_name("LINK")
+                                 .short("l")
+                                 .long("link")
+                                 .required(true)
+                                 .takes_value(true)))
+        .subcommand(clap::SubCommand::with_name("constrain")
+                        .about(CONSTRAIN_ABOUT)
+                        .arg(clap::Arg::with_name("NAME")
+                                 .help("The name of the alternatives that \
+                                       declare the constraint")
+                                 .value_name("NAME")
+                                 .short("n")
+                                 .long("name")
+                                 .required(true)
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("WHEN")
+                                 .help("The directory that targets of <NAME> \
+                                       must be under for the constraint to \
+                                       apply")
+                                 .value_name("WHEN")
+                                 .short("w")
+                                 .long("when")
+                                 .required(true)
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("REQUIRES")
+                                 .help("The name of the alternatives that \
+                                       are constrained")
+                                 .value_name("REQUIRES")
+                                 .short("r")
+                                 .long("requires")
+                                 .required(true)
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("UNDER")
+                                 .help("The directory that the target of \
+                                       <REQUIRES> must then be under, if not \
+                                       <WHEN>")
+                                 .value_name("UNDER")
+                                 .short("u")
+                                 .long("under")
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("REMOVE")
+                                 .help("Remove the constraint instead")
+                                 .long("remove")))
+        .subcommand(clap::SubCommand::with_name("resolve")
+                        .about(RESOLVE_ABOUT)
+                        .arg(clap::Arg::with_name("NAME")
+                                 .help("The name of the alternatives to \
+                                       resolve")
+                                 .value_name("NAME")
+                                 .short("n")
+                                 .long("name")
+                                 .required(true)
+                                 .takes_value(true)))
+        .subcommand(clap::SubCommand::with_name("set")
+                        .about(SET_ABOUT)
+                        .arg(clap::Arg::with_name("NAME")
+                                 .help("The name of the alternatives to \
+                                       select for")
+                                 .value_name("NAME")
+                                 .short("n")
+                                 .long("name")
+                                 .required(true)
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("TARGET")
+                                 .help("The path or file name of the \
+                                       alternative to select")
+                                 .value_name("TARGET")
+                                 .short("t")
+                                 .long("target")
+                                 .required_unless("AUTO")
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("FOR")
+                                 .help("How long to select <TARGET> for, \
+                                       such as 90s, 2h or 1d12h")
+                                 .value_name("DURATION")
+                                 .long("for")
+                                 .requires("TARGET")
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("AUTO")
+                                 .help("Go back to selecting by policy")
+                                 .long("auto")
+                                 .conflicts_with("TARGET")))
+        .subcommand(clap::SubCommand::with_name("hold")
+                        .about(HOLD_ABOUT)
+                        .arg(clap::Arg::with_name("NAME")
+                                 .help("The name of the alternatives to hold")
+                                 .value_name("NAME")
+                                 .short("n")
+                                 .long("name")
+                                 .required(true)
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("REASON")
+                                 .help("Why the alternatives are held")
+                                 .value_name("REASON")
+                                 .short("r")
+                                 .long("reason")
+                                 .takes_value(true)))
+        .subcommand(clap::SubCommand::with_name("unhold")
+                        .about(UNHOLD_ABOUT)
+                        .arg(clap::Arg::with_name("NAME")
+                                 .help("The name of the alternatives to \
+                                       release")
+                                 .value_name("NAME")
+                                 .short("n")
+                                 .long("name")
                                  .required(true)
                                  .takes_value(true)))
+        .subcommand(clap::SubCommand::with_name("prefer")
+                        .about(PREFER_ABOUT)
+                        .arg(clap::Arg::with_name("TAG")
+                                 .help("The tags to prefer, most preferred \
+                                       first")
+                                 .value_name("TAG")
+                                 .required(true)
+                                 .multiple(true)))
+        .subcommand(clap::SubCommand::with_name("configure")
+                        .about(CONFIGURE_ABOUT)
+                        .arg(clap::Arg::with_name("NAME")
+                                 .help("The name of the alternatives to \
+                                       configure")
+                                 .value_name("NAME")
+                                 .short("n")
+                                 .long("name")
+                                 .required_unless("GLOBAL")
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("GLOBAL")
+                                 .help("Configure every group that does not \
+                                       override the setting")
+                                 .short("g")
+                                 .long("global")
+                                 .conflicts_with("NAME"))
+                        .arg(clap::Arg::with_name("LINK_STRATEGY")
+                                 .help("How to install the link and its \
+                                       followers, or default to use the \
+                                       global setting")
+                                 .value_name("STRATEGY")
+                                 .short("l")
+                                 .long("link-strategy")
+                                 .possible_values(&["symlink",
+                                                    "relative-symlink",
+                                                    "hardlink", "copy",
+                                                    "default"])
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("POLICY")
+                                 .help("Whether to select the alternative \
+                                       with the highest priority or the \
+                                       highest version")
+                                 .value_name("POLICY")
+                                 .short("p")
+                                 .long("policy")
+                                 .possible_values(&["priority", "version"])
+                                 .requires("NAME")
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("TIE_POLICY")
+                                 .help("How to choose between usable \
+                                       alternatives of equal priority, or \
+                                       default to use the global setting")
+                                 .value_name("POLICY")
+                                 .short("t")
+                                 .long("tie-policy")
+                                 .possible_values(&["newest", "target",
+                                                    "error", "default"])
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("PREFERRED_TAGS")
+                                 .help("The comma separated tags whose \
+                                       alternatives are selected over any \
+                                       other, or default to use the global \
+                                       setting")
+                                 .value_name("TAGS")
+                                 .long("preferred-tags")
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("KIND")
+                                 .help("Whether the group is a link to an \
+                                       executable, a link to a directory or \
+                                       an environment variable")
+                                 .value_name("KIND")
+                                 .short("k")
+                                 .long("kind")
+                                 .possible_values(&["link", "directory",
+                                                    "variable"])
+                                 .requires("NAME")
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("VARIABLE")
+                                 .help("The environment variable to export the \
+                                       selection as")
+                                 .value_name("VARIABLE")
+                                 .long("variable")
+                                 .required_if("KIND", "variable")
+                                 .takes_value(true)))
+        .subcommand(clap::SubCommand::with_name("env")
+                        .about(ENV_ABOUT)
+                        .arg(clap::Arg::with_name("SHELL")
+                                 .help("The shell to print commands for, \
+                                       instead of the one in $SHELL")
+                                 .value_name("SHELL")
+                                 .short("s")
+                                 .long("shell")
+                                 .possible_values(&["sh", "bash", "zsh",
+                                                    "fish"])
+                                 .takes_value(true)))
+        .subcommand(clap::SubCommand::with_name("gen-hook")
+                        .about(GEN_HOOK_ABOUT)
+                        .arg(clap::Arg::with_name("FORMAT")
+                                 .help("The kind of hook to generate")
+                                 .value_name("FORMAT")
+                                 .short("f")
+                                 .long("format")
+                                 .possible_values(&["alpm", "rpm", "deb"])
+                                 .default_value("alpm")
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("NAME")
+                                 .help("The name of the alternatives to \
+                                       generate a hook for")
+                                 .value_name("NAME")
+                                 .short("n")
+                                 .long("name")
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("TARGET")
+                                 .help("Only include the alternative that \
+                                       points to this target")
+                                 .value_name("TARGET")
+                                 .short("t")
+                                 .long("target")
+                                 .requires("NAME")
+                                 .takes_value(true))
+                        .arg(clap::Arg::with_name("DIRECTORY")
+                                 .help("The directory that sync reads \
+                                       drop-ins from")
+                                 .value_name("DIRECTORY")
+                                 .short("d")
+                                 .long("directory")
+                                 .default_value("/usr/share/\
+                                                update-alternatives.d")
+                                 .takes_value(true)))
         .setting(clap::AppSettings::SubcommandRequiredElseHelp)
         .setting(clap::AppSettings::GlobalVersion)
 }
 
-static ABOUT: &'static str =
+static ABOUT: &str =
     "Manages symlinks to be placed in /usr/local/bin. Data is stored in \
     /etc/alternatives for persistence between invocations. Provides similar \
     functionality to Debian's update-alternatives, but with a slightly \
     different interface. Alternatives are selected by comparing their assigned \
-    priority values, with the highest priority being linked to.";
+    priority values, with the highest priority target that exists and is \
+    executable being linked to.";
 
-static LIST_ABOUT: &'static str =
+static LIST_ABOUT: &str =
     "Lists all alternatives for <NAME> and their assigned priority.";
 
-static ADD_ABOUT: &'static str =
+static ADD_ABOUT: &str =
     "Adds or modifies an alternative for <NAME> that points to <TARGET> with \
-    priority <WEIGHT>. If the database is modified, requires read/write access \
-    to /etc/alternatives and /usr/local/bin.";
+    priority <WEIGHT>. With --priority-from, the priority is instead derived \
+    from the version of <TARGET>, and derived again by refresh. If <OWNER> is \
+    given, it is recorded as one of the owners of the alternative. If this is \
+    the first alternative for <NAME>, the link is placed at <LINK>, or \
+    /usr/local/bin/<NAME> if it is not given. With --wrapper, the alternative \
+    is installed as a generated script that sets each <VAR=VALUE> and runs \
+    <TARGET> with each <ARG> before its own arguments. Each <TAG> labels the \
+    alternative for selection by preferred tags. The alternative is only \
+    selected while each <CONDITION> holds. A warning is printed if <TARGET> \
+    cannot run on this host, or with --strict, it is not added. If the \
+    database is modified, requires read/write access to /etc/alternatives \
+    and /usr/local/bin.";
 
-static REMOVE_ABOUT: &'static str =
+static REMOVE_ABOUT: &str =
     "If one exists, removes the alternative for <NAME> that points to \
-    <TARGET>. If the database is modified, requires read/write access to \
+    <TARGET>. If <OWNER> is given, instead releases its reference to every \
+    alternative it owns, optionally limited to <NAME> and <TARGET>, and \
+    removes the alternatives that are left without an owner, unless they \
+    were also added without one. If the database is modified, requires read/write access to /etc/alternatives and \
+    /usr/local/bin.";
+
+static DISCOVER_ABOUT: &str =
+    "Lists the executables in PATH, or in each <DIRECTORY>, whose file names \
+    match any <PATTERN>, along with the version found in their file names. \
+    With --register, adds each as an alternative for <NAME> with priority \
+    <WEIGHT>, or a priority derived from its version, so that gcc-14 is \
+    preferred over gcc-13. If the database is modified, requires read/write \
+    access to /etc/alternatives and /usr/local/bin.";
+
+static SYNC_ABOUT: &str =
+    "Reconciles the database with the drop-ins in <DIRECTORY>. Each drop-in is \
+    a JSON file with the fields name, target, priority and, optionally, \
+    followers, a map from follower link to follower target. Alternatives from \
+    new drop-ins are added and alternatives whose drop-in has disappeared \
+    from <DIRECTORY> are removed. Alternatives added by hand or from other \
+    directories are left alone. If the database is modified, requires \
+    read/write access to /etc/alternatives and /usr/local/bin.";
+
+static CHECK_ABOUT: &str =
+    "Reports every symlink or wrapper that differs from what would be \
+    created for the current selections, exiting unsuccessfully if any do. \
+    Such problems are repaired by refresh. Also warns about every target \
+    that cannot run on this host: ELF binaries built for another \
+    architecture or word size, and scripts whose interpreter is missing or \
+    not executable. With --strict, these warnings are problems as well.";
+
+static REFRESH_ABOUT: &str =
+    "Relinks every alternative to its highest priority target that still \
+    exists and is executable, reporting the targets that were skipped. \
+    Priorities derived from versions are derived again first. Requires \
+    read/write access to /etc/alternatives and /usr/local/bin.";
+
+static WATCH_ABOUT: &str =
+    "Watches /etc/alternatives and the directories containing every target, \
+    relinking the affected alternatives whenever a target appears or \
+    disappears or the database changes. Changes are batched until none have \
+    arrived for <DEBOUNCE> milliseconds. Runs until SIGTERM or SIGINT is \
+    received. Only supported on Linux. Requires read/write access to \
+    /usr/local/bin.";
+
+static EXEC_ABOUT: &str =
+    "Runs <COMMAND> with the alternatives selected by each <NAME=TARGET> \
+    placed first on its PATH, then exits with its exit status. The links are \
+    created in a private temporary directory that is removed afterwards, so \
+    the links in /usr/local/bin are left unchanged.";
+
+static SHELL_ABOUT: &str =
+    "Prints shell commands that put the alternatives selected by each \
+    <NAME=TARGET> first on PATH for the rest of the shell session, for use \
+    as eval \"$(update-alternatives shell --use NAME=TARGET)\". The links are \
+    created in a temporary directory named by UPDATE_ALTERNATIVES_SESSION, \
+    which later invocations in the same session add to. The links in \
+    /usr/local/bin are left unchanged.";
+
+static UNSHELL_ABOUT: &str =
+    "Prints shell commands that end the session started by shell, removing \
+    its directory from PATH, and removes the directory.";
+
+static SHIM_ABOUT: &str =
+    "Links <NAME> to the update-alternatives executable itself, which then \
+    acts as a shim. When invoked as <NAME>, it runs the target named by the \
+    nearest .alternatives file in the current directory or its ancestors, \
+    then by the UPDATE_ALTERNATIVES_<NAME> environment variable, and \
+    otherwise the target that would have been linked to. An .alternatives \
+    file contains lines of the form NAME=TARGET, where TARGET is the path or \
+    file name of an alternative for NAME. Requires read/write access to \
+    /etc/alternatives and /usr/local/bin.";
+
+static RELOCATE_ABOUT: &str =
+    "Moves the link for <NAME> to <LINK>, which must not be the link of \
+    another group. The existing link is renamed into place where possible, \
+    so that there is no moment without one. A move that cannot be completed \
+    is retried the next time the links are written. Held groups are not \
+    moved. Requires read/write access to /etc/alternatives and the \
+    directories of both links.";
+
+static CONSTRAIN_ABOUT: &str =
+    "Declares that while <NAME> selects a target under <WHEN>, <REQUIRES> \
+    must select a target under <UNDER>, or <WHEN> if it is not given. Every \
+    change to the database is refused while it leaves a constraint violated, \
+    unless --resolve is passed, in which case the groups that constraints \
+    require have a satisfying target selected by hand. With --remove, the \
+    constraint is removed instead. Requires read/write access to \
+    /etc/alternatives and /usr/local/bin.";
+
+static RESOLVE_ABOUT: &str =
+    "Prints the link for <NAME>, followed by its selected target. If that is \
+    the link of another group, such as c++ selecting /usr/local/bin/cc, the \
+    selection of that group follows, until a target that is not the link of \
+    any group is reached. Exits unsuccessfully if nothing is selected or the \
+    links form a cycle.";
+
+static SET_ABOUT: &str =
+    "Selects <TARGET> for <NAME> by hand, which may be the path or the file \
+    name of one of its alternatives, regardless of priorities, for as long as \
+    it is eligible. With --for, the selection reverts to what was selected \
+    before once <DURATION> has passed, which is noticed by every later \
+    invocation and by watch. With --auto, <NAME> goes back to being selected \
+    by its policy. Requires read/write access to /etc/alternatives and \
+    /usr/local/bin.";
+
+static HOLD_ABOUT: &str =
+    "Holds the alternatives for <NAME> at their current selection, recording \
+    the invoking user, taken from $SUDO_USER or $USER, and <REASON>. The link \
+    of a held group is left unchanged by add, remove, refresh, sync and \
+    every other subcommand unless --ignore-holds is passed. Requires \
+    read/write access to /etc/alternatives.";
+
+static UNHOLD_ABOUT: &str =
+    "Releases the hold on the alternatives for <NAME>, relinking them to the \
+    alternative they would otherwise select. Requires read/write access to \
+    /etc/alternatives and /usr/local/bin.";
+
+static PREFER_ABOUT: &str =
+    "Prefers the alternatives tagged with the first <TAG>, then those tagged \
+    with the next, over every other in each group that does not override its \
+    preferred tags, regardless of priority. Reports every group whose \
+    selection changes. Requires read/write access to /etc/alternatives and \
+    /usr/local/bin.";
+
+static CONFIGURE_ABOUT: &str =
+    "Changes the settings of the alternatives for <NAME>, or with --global, \
+    of every group that does not override them. The link strategy decides \
+    how the link and its followers are installed: as absolute symlinks, \
+    relative symlinks that survive relocating the tree, hardlinks or copies, \
+    for filesystems that do not support symlinks. Hardlinks and copies are \
+    only ever replaced if update-alternatives placed them and they have not \
+    been modified since. Alternatives with one of the preferred tags are \
+    ranked above all others, earlier tags first. Among them, the policy \
+    decides whether the usable alternative with the highest priority or the \
+    highest version is selected, and the tie policy which of several that \
+    rank equally is: the most recently added, the first target in \
+    lexicographic order, or none, in which case changes are refused until \
+    the tie is resolved. A group of the directory kind links to directories \
+    rather than executables, and is always installed as a symlink. A group of \
+    the variable kind installs no link, but has its selection exported as \
+    <VARIABLE> by /etc/profile.d/alternatives.sh and \
+    /etc/fish/conf.d/alternatives.fish. Requires read/write access to \
     /etc/alternatives and /usr/local/bin.";
+
+static ENV_ABOUT: &str =
+    "Prints shell commands that export the selection of every group of the \
+    variable kind, as found in /etc/profile.d/alternatives.sh.";
+
+static GEN_HOOK_ABOUT: &str =
+    "Prints a package manager hook. The alpm format is a pacman hook that runs \
+    sync whenever a drop-in in <DIRECTORY> is installed, upgraded or removed \
+    or, if <NAME> is given, runs refresh whenever one of the targets of <NAME> \
+    is. The rpm and deb formats are scriptlets and maintainer script snippets \
+    that add and remove the alternatives for <NAME>, or only the one pointing \
+    to <TARGET>, on behalf of the package. They recreate the link path, kind, \
+    wrapper, tags and conditions of the group. Alternatives with follower \
+    links cannot be added this way, so no hook is generated for them.";
@@ -0,0 +1,314 @@
+// Copyright (c) 2018, Gregory Meyer
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+#[cfg(unix)]
+extern crate libc;
+
+use super::alternative::Alternative;
+use super::alternative_db::AlternativeDb;
+use super::alternative_list;
+use super::filesystem;
+use super::filesystem::invalid;
+
+pub static SESSION_VAR: &str = "UPDATE_ALTERNATIVES_SESSION";
+
+// a private directory of links that reflects a set of selections, removed
+// when dropped unless kept
+pub struct LinkDir {
+    path: std::path::PathBuf,
+    keep: bool,
+}
+
+impl LinkDir {
+    pub fn create(db: &AlternativeDb, selections: &[&str], purpose: &str)
+    -> std::io::Result<LinkDir> {
+        let links = resolve_all(db, selections)?;
+
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        let path = std::env::temp_dir()
+            .join(format!("update-alternatives-{}-{}-{}", purpose,
+                          std::process::id(), nanos));
+
+        filesystem::create_private_dir(&path)?;
+
+        let link_dir = LinkDir{ path, keep: false };
+        link_dir.add_links(links)?;
+
+        Ok(link_dir)
+    }
+
+    // the link directory of the current shell session, if there is one. the
+    // directory itself may have been removed since
+    pub fn session() -> Option<LinkDir> {
+        let path = std::path::PathBuf::from(std::env::var_os(SESSION_VAR)?);
+        let file_name = path.file_name()?.to_string_lossy().into_owned();
+
+        if path.parent() != Some(&std::env::temp_dir())
+            || !file_name.starts_with("update-alternatives-shell-") {
+            return None;
+        }
+
+        Some(LinkDir{ path, keep: true })
+    }
+
+    pub fn update(&self, db: &AlternativeDb, selections: &[&str])
+    -> std::io::Result<()> {
+        let links = resolve_all(db, selections)?;
+
+        self.add_links(links)
+    }
+
+    fn add_links(&self, links: Vec<(std::ffi::OsString, Alternative)>)
+    -> std::io::Result<()> {
+        for (name, alternative) in links {
+            alternative_list::install(&self.path.join(name), &alternative)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    pub fn keep(&mut self) {
+        self.keep = true;
+    }
+
+    pub fn remove(mut self) -> std::io::Result<()> {
+        self.keep = true;
+
+        if !self.path.exists() {
+            return Ok(());
+        }
+
+        std::fs::remove_dir_all(&self.path)
+    }
+
+    pub fn prepend_to_path(&self) -> std::io::Result<std::ffi::OsString> {
+        let mut paths = vec![self.path.clone()];
+
+        if let Some(existing) = std::env::var_os("PATH") {
+            paths.extend(std::env::split_paths(&existing));
+        }
+
+        std::env::join_paths(paths).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, e)
+        })
+    }
+}
+
+impl Drop for LinkDir {
+    fn drop(&mut self) {
+        if self.keep {
+            return;
+        }
+
+        if let Err(e) = std::fs::remove_dir_all(&self.path) {
+            eprintln!("update-alternatives: could not remove {}: {}",
+                      self.path.display(), e);
+        }
+    }
+}
+
+// runs command with the link directory prepended to PATH, returning the exit
+// code to forward. interrupts are left to the child, so that the link
+// directory can still be removed once it exits
+pub fn run(link_dir: &LinkDir, command: &[std::ffi::OsString])
+-> std::io::Result<i32> {
+    let path = link_dir.prepend_to_path()?;
+
+    defer_interrupts()?;
+
+    let status = std::process::Command::new(&command[0])
+        .args(&command[1..])
+        .env("PATH", path)
+        .status()?;
+
+    Ok(exit_code(status))
+}
+
+#[cfg(unix)]
+extern "C" fn ignore(_: libc::c_int) { }
+
+// installs a handler rather than ignoring the signals outright, as ignored
+// signals would stay ignored in the child
+#[cfg(unix)]
+fn defer_interrupts() -> std::io::Result<()> {
+    filesystem::handle_signals(&[libc::SIGINT, libc::SIGQUIT, libc::SIGTERM],
+                               ignore)
+}
+
+#[cfg(windows)]
+fn defer_interrupts() -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn exit_code(status: std::process::ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+
+    match status.code() {
+        Some(c) => c,
+        None => 128 + status.signal().unwrap_or(0),
+    }
+}
+
+#[cfg(windows)]
+fn exit_code(status: std::process::ExitStatus) -> i32 {
+    status.code().unwrap_or(1)
+}
+
+#[derive(Clone, Copy)]
+pub enum Shell {
+    Posix,
+    Fish,
+}
+
+impl Shell {
+    // bash, zsh and anything unrecognized are treated as POSIX shells
+    pub fn from_name(name: &str) -> Shell {
+        let base = std::path::Path::new(name)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        if base == "fish" {
+            Shell::Fish
+        } else {
+            Shell::Posix
+        }
+    }
+
+    pub fn export(self, name: &str, value: &std::path::Path) -> String {
+        match self {
+            Shell::Posix => format!("export {}={};\n", name, posix_quote(value)),
+            Shell::Fish => format!("set -gx {} {};\n", name, fish_quote(value)),
+        }
+    }
+
+    pub fn unset(self, name: &str) -> String {
+        match self {
+            Shell::Posix => format!("unset {};\n", name),
+            Shell::Fish => format!("set -e {};\n", name),
+        }
+    }
+
+    pub fn prepend_path(self, directory: &std::path::Path) -> String {
+        match self {
+            Shell::Posix => format!("export PATH={}\"${{PATH:+:$PATH}}\";\n",
+                                    posix_quote(directory)),
+            Shell::Fish => format!("set -gx PATH {} $PATH;\n",
+                                   fish_quote(directory)),
+        }
+    }
+
+    pub fn set_path(self, paths: &[std::path::PathBuf])
+    -> std::io::Result<String> {
+        match self {
+            Shell::Posix => {
+                let joined = std::env::join_paths(paths).map_err(|e| {
+                    std::io::Error::new(std::io::ErrorKind::InvalidInput, e)
+                })?;
+
+                Ok(format!("export PATH={};\n",
+                           posix_quote(std::path::Path::new(&joined))))
+            },
+            Shell::Fish => {
+                let quoted: Vec<String> = paths.iter()
+                    .map(|p| fish_quote(p))
+                    .collect();
+
+                Ok(format!("set -gx PATH {};\n", quoted.join(" ")))
+            },
+        }
+    }
+
+    // makes the shell forget the locations of commands it has already found
+    pub fn rehash(self) -> String {
+        match self {
+            Shell::Posix => String::from("hash -r 2>/dev/null || true;\n"),
+            Shell::Fish => String::new(),
+        }
+    }
+}
+
+fn posix_quote(value: &std::path::Path) -> String {
+    filesystem::shell_quote(&value.to_string_lossy())
+}
+
+fn fish_quote(value: &std::path::Path) -> String {
+    format!("'{}'", value.to_string_lossy()
+                         .replace('\\', "\\\\")
+                         .replace('\'', "\\'"))
+}
+
+fn resolve_all(db: &AlternativeDb, selections: &[&str])
+-> std::io::Result<Vec<(std::ffi::OsString, Alternative)>> {
+    selections.iter().map(|s| resolve(db, s)).collect()
+}
+
+// parses a selection of the form NAME=TARGET, where TARGET is either the full
+// path or the file name of one of the alternatives for NAME, returning the
+// name of the link and the alternative it should point to
+pub fn resolve(db: &AlternativeDb, selection: &str)
+-> std::io::Result<(std::ffi::OsString, Alternative)> {
+    let (name, value) = match selection.find('=') {
+        Some(i) => (&selection[..i], &selection[i + 1..]),
+        None => return Err(invalid(format!("{} is not of the form \
+                                           NAME=TARGET", selection))),
+    };
+
+    let list = match db.alternatives(name) {
+        Some(l) => l,
+        None => return Err(invalid(format!("no alternatives found for {}",
+                                           name))),
+    };
+
+    let alternative = match list.find(value) {
+        Some(a) => a,
+        None => return Err(invalid(format!("{} is not an alternative for {}",
+                                           value, name))),
+    };
+
+    if let Some(reason) = list.ineligible_reason(alternative) {
+        return Err(invalid(format!("{} cannot be used for {}: {}", value,
+                                   name, reason)));
+    }
+
+    let link_name = match list.path().file_name() {
+        Some(n) => n.to_os_string(),
+        None => return Err(invalid(format!("{} has no file name",
+                                           list.path().display()))),
+    };
+
+    Ok((link_name, alternative.clone()))
+}
@@ -0,0 +1,189 @@
+// Copyright (c) 2018, Gregory Meyer
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+use super::alternative::WRAPPER_MARKER;
+use super::alternative_db::AlternativeDb;
+use super::filesystem;
+use super::overlay::Shell;
+
+// the scripts that export the selection of every variable group, along with
+// the shell that reads each
+pub static SCRIPTS: &[(&str, Shell)] = &[
+    ("/etc/profile.d/alternatives.sh", Shell::Posix),
+    ("/etc/fish/conf.d/alternatives.fish", Shell::Fish),
+];
+
+pub fn script(db: &AlternativeDb, shell: Shell) -> String {
+    let mut exports: Vec<(&str, &std::path::Path)> = db.lists()
+        .filter_map(|(_, list)| Some((list.variable()?,
+                                      list.select()?.target())))
+        .collect();
+    exports.sort();
+
+    // the marker goes on the second line, as in wrappers, so that
+    // filesystem::is_wrapper recognizes the scripts as generated
+    let mut script = format!("# alternatives exported as environment \
+                              variables\n{}\n", WRAPPER_MARKER);
+
+    for (name, value) in exports {
+        script.push_str(&shell.export(name, value));
+    }
+
+    script
+}
+
+fn has_variables(db: &AlternativeDb) -> bool {
+    db.lists().any(|(_, list)| list.variable().is_some())
+}
+
+// regenerates each script whose directory exists, or removes them if there
+// are no variable groups left. returns true if any script was changed
+pub fn write(db: &AlternativeDb) -> std::io::Result<bool> {
+    let mut changed = false;
+
+    for &(path, shell) in SCRIPTS.iter() {
+        let on_disk = filesystem::under_root(db.root(), path);
+
+        if !has_variables(db) {
+            if filesystem::is_wrapper(&on_disk) {
+                std::fs::remove_file(&on_disk)?;
+
+                eprintln!("update-alternatives: removed {}, as no alternatives \
+                          are exported", path);
+                changed = true;
+            }
+
+            continue;
+        }
+
+        if !on_disk.parent().is_some_and(std::path::Path::is_dir) {
+            continue;
+        }
+
+        let contents = script(db, shell);
+
+        if filesystem::read(&on_disk).ok().as_ref() == Some(&contents) {
+            continue;
+        }
+
+        if on_disk.symlink_metadata().is_ok()
+            && !filesystem::is_wrapper(&on_disk) {
+            return Err(not_generated(path));
+        }
+
+        filesystem::write(contents, &on_disk)?;
+
+        eprintln!("update-alternatives: wrote {}", path);
+        changed = true;
+    }
+
+    Ok(changed)
+}
+
+pub fn check(db: &AlternativeDb) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if !has_variables(db) {
+        return problems;
+    }
+
+    for &(path, shell) in SCRIPTS.iter() {
+        let on_disk = filesystem::under_root(db.root(), path);
+
+        if !on_disk.parent().is_some_and(std::path::Path::is_dir) {
+            continue;
+        }
+
+        match filesystem::read(&on_disk) {
+            Ok(ref c) if *c == script(db, shell) => (),
+            Ok(_) if !filesystem::is_wrapper(&on_disk) => {
+                problems.push(not_generated(path).to_string());
+            },
+            Ok(_) => problems.push(format!("{} is outdated", path)),
+            Err(_) => problems.push(format!("{} is missing", path)),
+        }
+    }
+
+    problems
+}
+
+fn not_generated(path: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::AlreadyExists,
+                        format!("{} is not managed by update-alternatives",
+                                path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::alternative::Alternative;
+    use super::super::alternative_list::GroupKind;
+
+    // a database under root whose only group exports JAVA_HOME
+    fn exporting(root: &std::path::Path) -> AlternativeDb {
+        let mut db = AlternativeDb::from_folder(
+            root.join("etc/alternatives")
+        ).unwrap();
+        db.set_root(Some(root.to_path_buf()));
+        db.add_alternative("java", Alternative::from_parts("/opt/jdk", 10))
+            .unwrap();
+        db.alternatives_mut("java").unwrap().set_kind(GroupKind::Variable{
+            name: String::from("JAVA_HOME"),
+        });
+
+        db
+    }
+
+    #[test]
+    fn replaces_only_generated_scripts() {
+        let scratch = filesystem::ScratchDir::new("profile");
+        let profile = scratch.path().join("etc/profile.d");
+        let script_path = profile.join("alternatives.sh");
+        std::fs::create_dir_all(&profile).unwrap();
+        std::fs::create_dir_all(scratch.path().join("opt/jdk")).unwrap();
+        std::fs::create_dir_all(scratch.path().join("opt/jre")).unwrap();
+        let mut db = exporting(scratch.path());
+
+        assert!(write(&db).unwrap());
+        assert!(filesystem::read(&script_path).unwrap()
+                    .contains("JAVA_HOME"));
+        assert!(!write(&db).unwrap());
+
+        db.add_alternative("java", Alternative::from_parts("/opt/jre", 20))
+            .unwrap();
+        assert!(write(&db).unwrap());
+        assert!(check(&db).is_empty());
+
+        filesystem::write("export PATH=/opt/bin\n", &script_path).unwrap();
+
+        assert!(write(&db).is_err());
+        assert_eq!(filesystem::read(&script_path).unwrap(),
+                   "export PATH=/opt/bin\n");
+        assert_eq!(check(&db).len(), 1);
+    }
+}
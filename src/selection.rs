@@ -0,0 +1,100 @@
+// Copyright (c) 2018, Gregory Meyer
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+use super::alternative::Alternative;
+
+// decides which of the eligible alternatives of a group is selected. library
+// users may implement this and hand it to AlternativeList::set_custom_policy
+pub trait SelectionPolicy {
+    // the eligible alternatives that this policy ranks highest. when more
+    // than one is returned, the tie policy of the group decides between them
+    fn best<'a>(&self, eligible: &[&'a Alternative]) -> Vec<&'a Alternative>;
+}
+
+// the policies that can be configured for a group in the database
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Policy {
+    // the highest priority
+    #[default]
+    Priority,
+    // the highest recorded version, then the highest priority. alternatives
+    // without a version rank below every other
+    Version,
+}
+
+impl Policy {
+    pub fn from_name(name: &str) -> Option<Policy> {
+        match name {
+            "priority" => Some(Policy::Priority),
+            "version" => Some(Policy::Version),
+            _ => None,
+        }
+    }
+
+    pub fn is_priority(&self) -> bool {
+        *self == Policy::Priority
+    }
+}
+
+impl SelectionPolicy for Policy {
+    fn best<'a>(&self, eligible: &[&'a Alternative]) -> Vec<&'a Alternative> {
+        match *self {
+            Policy::Priority => highest_by(eligible, |a| a.priority()),
+            Policy::Version => highest_by(eligible, |a| {
+                (a.version().cloned(), a.priority())
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for Policy {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match *self {
+            Policy::Priority => "priority",
+            Policy::Version => "version",
+        };
+
+        write!(formatter, "{}", name)
+    }
+}
+
+// the alternatives for which key is greatest, in their original order
+pub fn highest_by<'a, K: Ord, F: Fn(&Alternative) -> K>(
+    alternatives: &[&'a Alternative], key: F
+) -> Vec<&'a Alternative> {
+    let highest = match alternatives.iter().map(|a| key(a)).max() {
+        Some(k) => k,
+        None => return Vec::new(),
+    };
+
+    alternatives.iter()
+        .filter(|a| key(a) == highest)
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,131 @@
+// Copyright (c) 2018, Gregory Meyer
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+use super::filesystem::LinkStrategy;
+
+// how to choose between eligible alternatives that share the highest priority
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum TiePolicy {
+    // the most recently added, which is what update-alternatives always did
+    #[default]
+    Newest,
+    // the first target in lexicographic order
+    Target,
+    // refuse to select any of them
+    Error,
+}
+
+impl TiePolicy {
+    pub fn from_name(name: &str) -> Option<TiePolicy> {
+        match name {
+            "newest" => Some(TiePolicy::Newest),
+            "target" => Some(TiePolicy::Target),
+            "error" => Some(TiePolicy::Error),
+            _ => None,
+        }
+    }
+
+    pub fn is_newest(&self) -> bool {
+        *self == TiePolicy::Newest
+    }
+}
+
+impl std::fmt::Display for TiePolicy {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match *self {
+            TiePolicy::Newest => "newest",
+            TiePolicy::Target => "target",
+            TiePolicy::Error => "error",
+        };
+
+        write!(formatter, "{}", name)
+    }
+}
+
+// defaults that apply to every group that does not override them, stored in
+// the database folder as .settings.json
+#[derive(Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct Settings {
+    #[serde(default, skip_serializing_if = "LinkStrategy::is_symlink")]
+    link_strategy: LinkStrategy,
+    #[serde(default, skip_serializing_if = "TiePolicy::is_newest")]
+    tie_policy: TiePolicy,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    preferred_tags: Vec<String>,
+}
+
+impl Settings {
+    pub fn link_strategy(&self) -> LinkStrategy {
+        self.link_strategy
+    }
+
+    pub fn set_link_strategy(&mut self, strategy: LinkStrategy) -> bool {
+        if self.link_strategy == strategy {
+            return false;
+        }
+
+        self.link_strategy = strategy;
+
+        true
+    }
+
+    pub fn tie_policy(&self) -> TiePolicy {
+        self.tie_policy
+    }
+
+    pub fn set_tie_policy(&mut self, policy: TiePolicy) -> bool {
+        if self.tie_policy == policy {
+            return false;
+        }
+
+        self.tie_policy = policy;
+
+        true
+    }
+
+    // the tags whose alternatives are selected over every other, in order of
+    // preference
+    pub fn preferred_tags(&self) -> &[String] {
+        &self.preferred_tags
+    }
+
+    pub fn set_preferred_tags(&mut self, tags: Vec<String>) -> bool {
+        if self.preferred_tags == tags {
+            return false;
+        }
+
+        self.preferred_tags = tags;
+
+        true
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Settings::default()
+    }
+}
@@ -0,0 +1,196 @@
+// Copyright (c) 2018, Gregory Meyer
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+use super::alternative::{Alternative, LinkKind};
+use super::alternative_db::AlternativeDb;
+use super::alternative_list::AlternativeList;
+use super::filesystem::invalid;
+
+static OVERRIDE_FILE: &str = ".alternatives";
+
+// returns the name of the group and the group whose shim this executable was
+// invoked as, if any. only the link path of a group with its shim enabled
+// dispatches, so a renamed copy of update-alternatives still runs the command
+// line interface
+pub fn invoked_group<P: std::convert::AsRef<std::path::Path>>(folder: P)
+-> Option<(String, AlternativeList)> {
+    let argv0 = std::env::args_os().next()?;
+    let file_name = std::path::Path::new(&argv0).file_name()?;
+
+    if file_name == "update-alternatives" {
+        return None;
+    }
+
+    AlternativeDb::find_shim(folder, file_name).ok()?
+}
+
+pub fn dispatch(name: &str, list: &AlternativeList) -> ! {
+    let alternative = match resolve(name, list) {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("update-alternatives: could not resolve {}: {}", name, e);
+
+            std::process::exit(127);
+        }
+    };
+
+    let mut args = std::env::args_os();
+    let argv0 = args.next().unwrap();
+    let mut command = std::process::Command::new(alternative.target());
+
+    if let LinkKind::Wrapper{ args: ref fixed, ref env } = *alternative.kind() {
+        command.args(fixed).envs(env);
+    }
+
+    command.args(args);
+
+    exec(command, argv0, alternative.target())
+}
+
+// resolves the group name to a target, preferring an .alternatives file in
+// the current directory or one of its ancestors, then the environment, then
+// the selection of list
+pub fn resolve(name: &str, list: &AlternativeList)
+-> std::io::Result<Alternative> {
+    let requested = match find_override(name)? {
+        Some(r) => Some(r),
+        None => std::env::var(env_var(name)).ok(),
+    };
+
+    let alternative = match requested {
+        Some(r) => match list.find(&r) {
+            Some(a) => a,
+            None => return Err(invalid(format!("{} is not an alternative for \
+                                               {}", r, name))),
+        },
+        None => match list.select() {
+            Some(a) => a,
+            None => return Err(invalid(format!("none of the alternatives for \
+                                               {} are usable", name))),
+        },
+    };
+
+    if let Some(reason) = list.ineligible_reason(alternative) {
+        return Err(invalid(format!("{} cannot be used: {}",
+                                   alternative.target().display(), reason)));
+    }
+
+    let target = alternative.target();
+
+    if target.canonicalize().ok() == std::env::current_exe().ok() {
+        return Err(invalid(format!("{} points back to the shim",
+                                   target.display())));
+    }
+
+    Ok(alternative.clone())
+}
+
+// the name of the environment variable that overrides name, such as
+// UPDATE_ALTERNATIVES_CC for cc
+pub fn env_var(name: &str) -> String {
+    let suffix: String = name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    format!("UPDATE_ALTERNATIVES_{}", suffix)
+}
+
+fn find_override(name: &str) -> std::io::Result<Option<String>> {
+    let current = std::env::current_dir()?;
+
+    for directory in current.ancestors() {
+        let path = directory.join(OVERRIDE_FILE);
+
+        if !path.is_file() {
+            continue;
+        }
+
+        if let Some(value) = parse_overrides(&path)?.remove(name) {
+            return Ok(Some(value));
+        }
+    }
+
+    Ok(None)
+}
+
+// reads lines of the form NAME=TARGET, ignoring blank lines and comments
+fn parse_overrides(path: &std::path::Path)
+-> std::io::Result<std::collections::HashMap<String, String>> {
+    let contents = super::filesystem::read(path)?;
+    let mut overrides = std::collections::HashMap::new();
+
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match line.find('=') {
+            Some(i) => {
+                overrides.insert(line[..i].trim().to_string(),
+                                 line[i + 1..].trim().to_string());
+            },
+            None => eprintln!("update-alternatives: ignoring malformed line \
+                              in {}: {}", path.display(), line),
+        }
+    }
+
+    Ok(overrides)
+}
+
+#[cfg(unix)]
+fn exec(mut command: std::process::Command, argv0: std::ffi::OsString,
+        target: &std::path::Path) -> ! {
+    use std::os::unix::process::CommandExt;
+
+    let e = command.arg0(argv0).exec();
+
+    eprintln!("update-alternatives: could not execute {}: {}",
+              target.display(), e);
+
+    std::process::exit(126);
+}
+
+#[cfg(windows)]
+fn exec(mut command: std::process::Command, _: std::ffi::OsString,
+        target: &std::path::Path) -> ! {
+    match command.status() {
+        Ok(s) => std::process::exit(s.code().unwrap_or(1)),
+        Err(e) => {
+            eprintln!("update-alternatives: could not execute {}: {}",
+                      target.display(), e);
+
+            std::process::exit(126);
+        }
+    }
+}
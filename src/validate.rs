@@ -0,0 +1,138 @@
+// Copyright (c) 2018, Gregory Meyer
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+use std::io::Read;
+
+use super::elf;
+use super::filesystem;
+
+// the longest shebang line that is inspected, which is longer than any
+// kernel accepts
+static SHEBANG_LIMIT: usize = 256;
+
+// why the executable target, found at on_disk, cannot run on this host if
+// it cannot: an ELF binary built for another architecture, or a script whose
+// interpreter is missing. interpreters are looked up under root. targets in
+// any other format are assumed to run
+pub fn run_problem(target: &std::path::Path, on_disk: &std::path::Path,
+                   root: Option<&std::path::Path>) -> Option<String> {
+    if !on_disk.is_file() {
+        return None;
+    }
+
+    match elf::Header::read(on_disk) {
+        Ok(Some(ref h)) if h.is_compatible() => return None,
+        Ok(Some(h)) => {
+            return Some(format!("{} is a {} ELF binary, but this host is {}",
+                                target.display(), h, elf::native()));
+        },
+        Ok(None) => (),
+        Err(e) => {
+            return Some(format!("could not read {}: {}", target.display(), e));
+        },
+    }
+
+    let line = match shebang(on_disk) {
+        Ok(Some(l)) => l,
+        Ok(None) => return None,
+        Err(e) => {
+            return Some(format!("could not read {}: {}", target.display(), e));
+        },
+    };
+
+    let mut words = line.split_whitespace();
+
+    let interpreter = match words.next() {
+        Some(i) => std::path::PathBuf::from(i),
+        None => {
+            return Some(format!("{} has a shebang without an interpreter",
+                                target.display()));
+        },
+    };
+
+    if let Some(problem) = interpreter_problem(target, &interpreter, root) {
+        return Some(problem);
+    }
+
+    // /usr/bin/env looks the actual interpreter up on PATH
+    if interpreter.file_name() != Some(std::ffi::OsStr::new("env")) {
+        return None;
+    }
+
+    let program = words.find(|w| !w.starts_with('-') && !w.contains('='))?;
+
+    if program.contains('/') {
+        return interpreter_problem(target, std::path::Path::new(program),
+                                   root);
+    }
+
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let found = std::env::split_paths(&path).any(|d| {
+        filesystem::is_executable(filesystem::under_root(root, d.join(program)))
+    });
+
+    if found {
+        None
+    } else {
+        Some(format!("the interpreter {} of {} is not on PATH", program,
+                     target.display()))
+    }
+}
+
+fn interpreter_problem(target: &std::path::Path,
+                       interpreter: &std::path::Path,
+                       root: Option<&std::path::Path>) -> Option<String> {
+    let on_disk = filesystem::under_root(root, interpreter);
+
+    if !on_disk.exists() {
+        Some(format!("the interpreter {} of {} does not exist",
+                     interpreter.display(), target.display()))
+    } else if !filesystem::is_executable(&on_disk) {
+        Some(format!("the interpreter {} of {} is not executable",
+                     interpreter.display(), target.display()))
+    } else {
+        None
+    }
+}
+
+// the rest of the first line of path if it starts with #!
+fn shebang(path: &std::path::Path) -> std::io::Result<Option<String>> {
+    let mut buffer = Vec::new();
+
+    std::fs::File::open(path)?
+        .take(SHEBANG_LIMIT as u64)
+        .read_to_end(&mut buffer)?;
+
+    if !buffer.starts_with(b"#!") {
+        return Ok(None);
+    }
+
+    let line = buffer[2..].split(|&b| b == b'\n').next().unwrap_or(&[]);
+
+    Ok(Some(String::from_utf8_lossy(line).into_owned()))
+}
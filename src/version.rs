@@ -0,0 +1,257 @@
+// Copyright (c) 2018, Gregory Meyer
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+// a dotted numeric version, such as the 3.12 in python3.12
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[serde(into = "String", try_from = "String")]
+pub struct Version {
+    components: Vec<u32>,
+}
+
+impl Version {
+    // the last run of digits and dots in name, so that gcc-14 yields 14,
+    // python3.12 yields 3.12 and x86_64-linux-gnu-gcc-13 yields 13
+    pub fn from_file_name(name: &str) -> Option<Version> {
+        let end = name.rfind(|c: char| c.is_ascii_digit())? + 1;
+        let start = name[..end]
+            .rfind(|c: char| !c.is_ascii_digit() && c != '.')
+            .map_or(0, |i| i + 1);
+
+        name[start..end].trim_start_matches('.').parse().ok()
+    }
+
+    // a priority that orders versions the same way, as long as the minor and
+    // patch components stay below 100
+    pub fn priority(&self) -> i32 {
+        let component = |i: usize, max: u32| {
+            i64::from(self.components.get(i).cloned().unwrap_or(0).min(max))
+        };
+
+        let priority = component(0, 21_474) * 10_000 + component(1, 99) * 100
+            + component(2, 99);
+
+        priority as i32
+    }
+}
+
+impl std::str::FromStr for Version {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Version, String> {
+        let components: Result<Vec<u32>, _> = s.split('.')
+            .map(str::parse)
+            .collect();
+
+        match components {
+            Ok(ref c) if c.is_empty() => Err(format!("{} is not a version", s)),
+            Ok(c) => Ok(Version{ components: c }),
+            Err(_) => Err(format!("{} is not a version", s)),
+        }
+    }
+}
+
+impl std::convert::TryFrom<String> for Version {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Version, String> {
+        s.parse()
+    }
+}
+
+impl From<Version> for String {
+    fn from(version: Version) -> String {
+        version.to_string()
+    }
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let components: Vec<String> = self.components
+            .iter()
+            .map(u32::to_string)
+            .collect();
+
+        write!(formatter, "{}", components.join("."))
+    }
+}
+
+// where the version of an alternative whose priority is derived from it is
+// found
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum VersionSource {
+    FileName,
+    Output,
+}
+
+impl std::fmt::Display for VersionSource {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match *self {
+            VersionSource::FileName => "version",
+            VersionSource::Output => "version-output",
+        };
+
+        write!(formatter, "{}", name)
+    }
+}
+
+// how long to wait for target --version to exit
+static PROBE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+impl VersionSource {
+    pub fn from_name(name: &str) -> Option<VersionSource> {
+        match name {
+            "version" => Some(VersionSource::FileName),
+            "version-output" => Some(VersionSource::Output),
+            _ => None,
+        }
+    }
+
+    // the version of target, which is found at on_disk, if there is one.
+    // failures to run target are reported and treated as having no version
+    pub fn find(self, target: &std::path::Path,
+                on_disk: &std::path::Path) -> Option<Version> {
+        match self {
+            VersionSource::FileName => {
+                Version::from_file_name(&target.file_name()?.to_string_lossy())
+            },
+            VersionSource::Output => match probe(on_disk) {
+                Ok(o) => Version::from_output(&o),
+                Err(e) => {
+                    eprintln!("update-alternatives: could not run {} \
+                              --version: {}", target.display(), e);
+
+                    None
+                },
+            },
+        }
+    }
+}
+
+impl Version {
+    // the first token of output that looks like a version with at least two
+    // components, such as the 14.2.1 in "gcc (GCC) 14.2.1 20240910"
+    pub fn from_output(output: &str) -> Option<Version> {
+        output.split_whitespace()
+            .filter_map(Version::from_file_name)
+            .find(|v| v.components.len() >= 2)
+    }
+}
+
+// the combined standard output and error of executable --version, as some
+// programs print their version to either. both are read while waiting, so
+// that a long version message cannot fill a pipe and block the program
+fn probe(executable: &std::path::Path) -> std::io::Result<String> {
+    let mut child = std::process::Command::new(executable)
+        .arg("--version")
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()?;
+
+    let stdout = child.stdout.take().map(drain);
+    let stderr = child.stderr.take().map(drain);
+    let started = std::time::Instant::now();
+
+    while child.try_wait()?.is_none() {
+        if started.elapsed() > PROBE_TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+
+            return Err(std::io::Error::new(std::io::ErrorKind::TimedOut,
+                                           "timed out"));
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    let mut output = Vec::new();
+
+    for reader in stdout.into_iter().chain(stderr) {
+        match reader.join() {
+            Ok(read) => output.extend(read?),
+            Err(_) => return Err(std::io::Error::other("could not read the \
+                                                        output")),
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&output).into_owned())
+}
+
+// reads from pipe until it is closed on a separate thread
+fn drain<R: std::io::Read + Send + 'static>(mut pipe: R)
+-> std::thread::JoinHandle<std::io::Result<Vec<u8>>> {
+    std::thread::spawn(move || {
+        let mut read = Vec::new();
+        pipe.read_to_end(&mut read)?;
+
+        Ok(read)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(name: &str) -> Option<String> {
+        Version::from_file_name(name).map(|v| v.to_string())
+    }
+
+    #[test]
+    fn finds_versions_in_file_names() {
+        assert_eq!(version("gcc-14"), Some(String::from("14")));
+        assert_eq!(version("python3.12"), Some(String::from("3.12")));
+        assert_eq!(version("ld.lld-18.1.8"), Some(String::from("18.1.8")));
+        assert_eq!(version("node-v20."), Some(String::from("20")));
+        assert_eq!(version("x86_64-linux-gnu-gcc-13"),
+                   Some(String::from("13")));
+        assert_eq!(version("x86_64-linux-gnu-gcc-14"),
+                   Some(String::from("14")));
+        assert_eq!(version("clang"), None);
+    }
+
+    #[test]
+    fn priorities_order_like_versions() {
+        let priority = |s: &str| s.parse::<Version>().unwrap().priority();
+
+        assert_eq!(priority("14.2.1"), 140_201);
+        assert_eq!(priority("3"), 30_000);
+        assert!(priority("3.12") > priority("3.9"));
+        assert!(priority("10.0") > priority("9.99.99"));
+        assert_eq!(priority("1.500"), priority("1.99"));
+    }
+
+    #[test]
+    fn finds_versions_in_output() {
+        let output = "gcc (GCC) 14.2.1 20240910\nCopyright (C) 2024";
+
+        assert_eq!(Version::from_output(output).map(|v| v.to_string()),
+                   Some(String::from("14.2.1")));
+        assert!(Version::from_output("built in 2024").is_none());
+    }
+}
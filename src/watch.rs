@@ -0,0 +1,548 @@
+// Copyright (c) 2018, Gregory Meyer
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright
+//       notice, this list of conditions and the following disclaimer in the
+//       documentation and/or other materials provided with the distribution.
+//     * Neither the name of the <organization> nor the
+//       names of its contributors may be used to endorse or promote products
+//       derived from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED. IN NO EVENT SHALL <COPYRIGHT HOLDER> BE LIABLE FOR ANY 
+// DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES
+// (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES;
+// LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND
+// ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+extern crate std;
+
+extern crate libc;
+
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::alternative_db::AlternativeDb;
+use super::filesystem;
+use super::profile;
+
+static TERMINATED: AtomicBool = AtomicBool::new(false);
+
+static TERMINATING: [libc::c_int; 2] = [libc::SIGTERM, libc::SIGINT];
+
+const WATCH_MASK: u32 = libc::IN_CREATE | libc::IN_DELETE | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO | libc::IN_CLOSE_WRITE | libc::IN_ATTRIB;
+
+pub fn watch<P: std::convert::AsRef<std::path::Path>>(
+    folder: P, root: Option<&std::path::Path>, debounce: std::time::Duration
+) -> std::io::Result<()> {
+    let folder_path = folder.as_ref();
+
+    filesystem::create_dir(folder_path)?;
+    filesystem::handle_signals(&TERMINATING, terminate)?;
+    // the signals are only delivered while waiting for events, so one that
+    // arrives after the flag is checked still interrupts the wait
+    block(&TERMINATING)?;
+
+    let mut session = Session::new(folder_path, root)?;
+
+    while !TERMINATED.load(Ordering::SeqCst) {
+        session.step(debounce)?;
+    }
+
+    println!("update-alternatives: received termination signal, exiting");
+
+    Ok(())
+}
+
+// the database and the changes seen since it was last refreshed
+struct Session<'a> {
+    folder: &'a std::path::Path,
+    root: Option<&'a std::path::Path>,
+    watcher: Watcher,
+    db: AlternativeDb,
+    changed: Vec<std::path::PathBuf>,
+}
+
+impl<'a> Session<'a> {
+    fn new(folder: &'a std::path::Path, root: Option<&'a std::path::Path>)
+    -> std::io::Result<Session<'a>> {
+        let mut watcher = Watcher::new()?;
+        let mut db = AlternativeDb::from_folder(folder)?;
+        db.set_root(root.map(std::path::Path::to_path_buf));
+
+        watcher.add_watches(folder, &db);
+        println!("update-alternatives: watching {} and the targets of {} \
+                 alternatives", folder.display(), db.num_alternatives());
+        refresh(&mut db, folder, |_| true);
+
+        Ok(Session{ folder, root, watcher, db, changed: Vec::new() })
+    }
+
+    // waits for changes, returning false as soon as some arrive. once no
+    // more arrive for debounce, or a selection expires, applies them and
+    // returns true
+    fn step(&mut self, debounce: std::time::Duration)
+    -> std::io::Result<bool> {
+        let expiry = self.db.next_expiry().map(|t| {
+            std::time::Duration::from_secs(t.saturating_sub(self.db.now()))
+        });
+
+        let timeout = match (self.changed.is_empty(), expiry) {
+            (true, e) => e,
+            (false, Some(e)) => Some(std::cmp::min(debounce, e)),
+            (false, None) => Some(debounce),
+        };
+
+        if self.watcher.wait(timeout)? {
+            self.changed.extend(self.watcher.read_events()?);
+
+            return Ok(false);
+        }
+
+        if self.db.expire() {
+            if let Err(e) = self.db.write_out(self.folder) {
+                eprintln!("update-alternatives: could not commit changes to \
+                          {}: {}", self.folder.display(), e);
+            }
+
+            refresh(&mut self.db, self.folder, |_| true);
+        }
+
+        if self.changed.is_empty() {
+            return Ok(true);
+        }
+
+        if self.changed.iter().any(|p| p.parent() == Some(self.folder)) {
+            println!("update-alternatives: {} changed, reloading",
+                     self.folder.display());
+            self.db = AlternativeDb::from_folder(self.folder)?;
+            self.db.set_root(self.root.map(std::path::Path::to_path_buf));
+            refresh(&mut self.db, self.folder, |_| true);
+        } else {
+            for path in self.changed.iter() {
+                println!("update-alternatives: {} changed", path.display());
+            }
+
+            let changed = &self.changed;
+
+            refresh(&mut self.db, self.folder,
+                    |t| changed.iter().any(|p| t.starts_with(p)));
+        }
+
+        self.watcher.add_watches(self.folder, &self.db);
+        self.changed.clear();
+
+        Ok(true)
+    }
+}
+
+fn refresh<F: Fn(&std::path::Path) -> bool>(db: &mut AlternativeDb,
+                                            folder: &std::path::Path,
+                                            affects: F) {
+    let mut record_changed = false;
+
+    // selections may have changed since the chains were last resolved
+    db.link_chains();
+
+    for (name, list) in db.lists_mut() {
+        if !list.alternatives()
+                .iter()
+                .filter_map(|a| list.resolved(a.target()))
+                .any(|t| affects(&list.on_disk(t))) {
+            continue;
+        }
+
+        println!("update-alternatives: re-evaluating alternatives for {}",
+                 name);
+
+        if let Err(e) = list.make_symlink() {
+            eprintln!("update-alternatives: could not write symlink for {}: \
+                      {}", name, e);
+        }
+
+        if list.take_record_changed() {
+            record_changed = true;
+        }
+    }
+
+    if let Err(e) = profile::write(db) {
+        eprintln!("update-alternatives: could not write profile scripts: {}",
+                  e);
+    }
+
+    if record_changed {
+        if let Err(e) = db.write_out(folder) {
+            eprintln!("update-alternatives: could not commit changes to {}: \
+                      {}", folder.display(), e);
+        }
+    }
+}
+
+extern "C" fn terminate(_: libc::c_int) {
+    TERMINATED.store(true, Ordering::SeqCst);
+}
+
+fn block(signals: &[libc::c_int]) -> std::io::Result<()> {
+    let result = unsafe {
+        let mut set: libc::sigset_t = std::mem::zeroed();
+        libc::sigemptyset(&mut set);
+
+        for &signal in signals {
+            libc::sigaddset(&mut set, signal);
+        }
+
+        libc::pthread_sigmask(libc::SIG_BLOCK, &set, std::ptr::null_mut())
+    };
+
+    if result != 0 {
+        return Err(std::io::Error::from_raw_os_error(result));
+    }
+
+    Ok(())
+}
+
+struct Watcher {
+    fd: libc::c_int,
+    directories: std::collections::HashMap<libc::c_int, std::path::PathBuf>,
+    // the signal mask while waiting, which lets the terminating signals
+    // through even if they are blocked otherwise
+    waiting_mask: libc::sigset_t,
+}
+
+impl Watcher {
+    fn new() -> std::io::Result<Watcher> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        let waiting_mask = unsafe {
+            let mut mask: libc::sigset_t = std::mem::zeroed();
+            libc::pthread_sigmask(libc::SIG_BLOCK, std::ptr::null(),
+                                  &mut mask);
+
+            for &signal in TERMINATING.iter() {
+                libc::sigdelset(&mut mask, signal);
+            }
+
+            mask
+        };
+
+        Ok(Watcher{ fd, directories: std::collections::HashMap::new(),
+                    waiting_mask })
+    }
+
+    // watches the admin folder and the nearest existing ancestor of every
+    // target, so that targets in directories that do not exist yet are noticed
+    fn add_watches(&mut self, folder: &std::path::Path, db: &AlternativeDb) {
+        let mut directories = vec![folder.to_path_buf()];
+
+        for (_, list) in db.lists() {
+            for alternative in list.alternatives().iter() {
+                let target = list.on_disk(alternative.target());
+                let mut directory = target.parent();
+
+                while let Some(d) = directory {
+                    if d.is_dir() {
+                        directories.push(d.to_path_buf());
+
+                        break;
+                    }
+
+                    directory = d.parent();
+                }
+            }
+        }
+
+        for directory in directories {
+            if self.directories.values().any(|d| *d == directory) {
+                continue;
+            }
+
+            if let Err(e) = self.add_watch(&directory) {
+                eprintln!("update-alternatives: could not watch {}: {}",
+                          directory.display(), e);
+            }
+        }
+    }
+
+    fn add_watch(&mut self, directory: &std::path::Path)
+    -> std::io::Result<()> {
+        let mut bytes = directory.as_os_str().as_bytes().to_vec();
+        bytes.push(0);
+
+        let wd = unsafe {
+            libc::inotify_add_watch(self.fd,
+                                    bytes.as_ptr() as *const libc::c_char,
+                                    WATCH_MASK)
+        };
+
+        if wd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        self.directories.insert(wd, directory.to_path_buf());
+
+        Ok(())
+    }
+
+    // returns true if there are events to read, false if the timeout expired
+    // or a signal was received
+    fn wait(&self, timeout: Option<std::time::Duration>)
+    -> std::io::Result<bool> {
+        let mut poll_fd = libc::pollfd{ fd: self.fd, events: libc::POLLIN,
+                                        revents: 0 };
+        let timespec = timeout.map(|t| libc::timespec{
+            tv_sec: t.as_secs() as libc::time_t,
+            tv_nsec: t.subsec_nanos() as libc::c_long,
+        });
+        let timespec_ptr = match timespec {
+            Some(ref t) => t as *const libc::timespec,
+            None => std::ptr::null(),
+        };
+
+        match unsafe {
+            libc::ppoll(&mut poll_fd, 1, timespec_ptr, &self.waiting_mask)
+        } {
+            n if n < 0 => {
+                let error = std::io::Error::last_os_error();
+
+                if error.kind() == std::io::ErrorKind::Interrupted {
+                    Ok(false)
+                } else {
+                    Err(error)
+                }
+            },
+            0 => Ok(false),
+            _ => Ok(true),
+        }
+    }
+
+    fn read_events(&mut self) -> std::io::Result<Vec<std::path::PathBuf>> {
+        let mut buffer = vec![0u8; 4096];
+        let read = unsafe {
+            libc::read(self.fd, buffer.as_mut_ptr() as *mut libc::c_void,
+                       buffer.len())
+        };
+
+        if read < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        let header_size = std::mem::size_of::<libc::inotify_event>();
+        let mut paths = Vec::new();
+        let mut offset = 0;
+
+        while offset + header_size <= read as usize {
+            let event: libc::inotify_event = unsafe {
+                std::ptr::read_unaligned(
+                    buffer.as_ptr().add(offset) as *const libc::inotify_event
+                )
+            };
+
+            let name_start = offset + header_size;
+            let name_end = name_start + event.len as usize;
+            offset = name_end;
+
+            if event.mask & libc::IN_IGNORED != 0 {
+                self.directories.remove(&event.wd);
+
+                continue;
+            }
+
+            let directory = match self.directories.get(&event.wd) {
+                Some(d) => d,
+                None => continue,
+            };
+
+            let name: Vec<u8> = buffer[name_start..name_end]
+                .iter()
+                .cloned()
+                .take_while(|&b| b != 0)
+                .collect();
+
+            paths.push(directory.join(std::ffi::OsString::from_vec(name)));
+        }
+
+        Ok(paths)
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::alternative::Alternative;
+
+    static DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(50);
+
+    // a scratch tree standing in for the root of a system, removed when it
+    // is dropped
+    struct Root(std::path::PathBuf);
+
+    impl Root {
+        fn new(name: &str) -> Root {
+            let path = std::env::temp_dir()
+                .join(format!("update-alternatives-{}-{}", name,
+                              std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+
+            for directory in ["etc/alternatives", "usr/local/bin",
+                              "opt"].iter() {
+                std::fs::create_dir_all(path.join(directory)).unwrap();
+            }
+
+            Root(path)
+        }
+
+        fn folder(&self) -> std::path::PathBuf {
+            self.0.join("etc/alternatives")
+        }
+
+        fn on_disk(&self, path: &str) -> std::path::PathBuf {
+            filesystem::under_root(Some(&self.0), path)
+        }
+
+        // adds an alternative for cc to the database on disk
+        fn register(&self, target: &str, priority: i32) {
+            self.register_for("cc", target, priority);
+        }
+
+        fn register_for(&self, name: &str, target: &str, priority: i32) {
+            let mut db = AlternativeDb::from_folder(self.folder()).unwrap();
+            db.set_root(Some(self.0.clone()));
+            db.add_alternative(name, Alternative::from_parts(target, priority))
+                .unwrap();
+            db.write_out(self.folder()).unwrap();
+        }
+
+        fn install(&self, target: &str) {
+            filesystem::write_script("#!/bin/sh\n", self.on_disk(target))
+                .unwrap();
+        }
+
+        fn uninstall(&self, target: &str) {
+            std::fs::remove_file(self.on_disk(target)).unwrap();
+        }
+
+        fn link(&self) -> Option<std::path::PathBuf> {
+            self.link_for("cc")
+        }
+
+        fn link_for(&self, name: &str) -> Option<std::path::PathBuf> {
+            self.on_disk(&format!("/usr/local/bin/{}", name)).read_link().ok()
+        }
+    }
+
+    impl Drop for Root {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    // steps session until it applies the changes it has seen. there must be
+    // some, or this waits forever
+    fn settle(session: &mut Session) {
+        for _ in 0..100 {
+            if session.step(DEBOUNCE).unwrap() {
+                return;
+            }
+        }
+
+        panic!("changes kept arriving");
+    }
+
+    #[test]
+    fn relinks_when_target_appears_and_disappears() {
+        let root = Root::new("appear");
+        root.register("/opt/cc-a", 10);
+
+        let folder = root.folder();
+        let mut session = Session::new(&folder, Some(&root.0)).unwrap();
+        assert_eq!(root.link(), None);
+
+        root.install("/opt/cc-a");
+        settle(&mut session);
+        assert_eq!(root.link(), Some(std::path::PathBuf::from("/opt/cc-a")));
+
+        root.uninstall("/opt/cc-a");
+        settle(&mut session);
+        assert_eq!(root.link(), None);
+    }
+
+    #[test]
+    fn waits_for_changes_to_settle() {
+        let root = Root::new("debounce");
+        root.register("/opt/cc-a", 10);
+        root.register("/opt/cc-b", 20);
+
+        let folder = root.folder();
+        let mut session = Session::new(&folder, Some(&root.0)).unwrap();
+
+        root.install("/opt/cc-a");
+        assert!(!session.step(DEBOUNCE).unwrap());
+        assert_eq!(root.link(), None);
+
+        root.install("/opt/cc-b");
+        settle(&mut session);
+        assert_eq!(root.link(), Some(std::path::PathBuf::from("/opt/cc-b")));
+    }
+
+    #[test]
+    fn reloads_when_database_changes() {
+        let root = Root::new("reload");
+        root.install("/opt/cc-a");
+
+        let folder = root.folder();
+        let mut session = Session::new(&folder, Some(&root.0)).unwrap();
+        assert!(!session.db.has_alternatives("cc"));
+
+        root.register("/opt/cc-a", 10);
+        settle(&mut session);
+        assert!(session.db.has_alternatives("cc"));
+        assert_eq!(root.link(), Some(std::path::PathBuf::from("/opt/cc-a")));
+    }
+
+    #[test]
+    fn resolves_chains_against_current_selections() {
+        let root = Root::new("chain");
+        root.register("/opt/clang", 10);
+        root.register("/opt/gcc", 20);
+        root.register_for("c++", "/usr/local/bin/cc", 10);
+        root.install("/opt/clang");
+
+        let folder = root.folder();
+        let mut session = Session::new(&folder, Some(&root.0)).unwrap();
+        assert_eq!(root.link(), Some(std::path::PathBuf::from("/opt/clang")));
+        assert_eq!(root.link_for("c++"),
+                   Some(std::path::PathBuf::from("/usr/local/bin/cc")));
+
+        root.install("/opt/gcc");
+        settle(&mut session);
+        assert_eq!(root.link(), Some(std::path::PathBuf::from("/opt/gcc")));
+
+        root.uninstall("/opt/clang");
+        settle(&mut session);
+        assert_eq!(root.link(), Some(std::path::PathBuf::from("/opt/gcc")));
+        assert_eq!(root.link_for("c++"),
+                   Some(std::path::PathBuf::from("/usr/local/bin/cc")));
+    }
+}